@@ -1,23 +1,72 @@
 use crate::error::BelError;
+use crate::loader;
+use crate::module::{Module, CORE};
 use crate::object::Object;
 use crate::primatives::{load_primatives, PrimFunc};
+use crate::printer::{is_string, string_chars};
+use crate::source::Span;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct Environment {
+    /// the core namespace, shared by every module
     pub globals: HashMap<String, Object>,
+    pub modules: HashMap<String, Module>,
+    // where set and def bind names; None is core
+    current_module: Option<String>,
+    /// directories (load name) looks in when name is not found
+    /// next to the file loading it
+    pub search_path: Vec<PathBuf>,
+    // the files being loaded, innermost last
+    pub(crate) loading: Vec<PathBuf>,
+    // where loaded files defined globals, keyed as lookup finds them
+    pub(crate) sources: HashMap<String, Span>,
+    // set to stop the evaluation in progress
+    interrupt: Interrupter,
+    // while a form is evaluated atomically, the bindings it replaced
+    undo: Option<Vec<Replaced>>,
     primatives: HashMap<String, PrimFunc>,
 }
 
+// a binding set replaced: the module, the name and the value it had, if any
+type Replaced = (Option<String>, String, Option<Object>);
+
+/// A handle that stops an environment's evaluation from another thread,
+/// such as a signal handler. The evaluation in progress, or the next one
+/// to start, fails with BelError::Interrupted; globals set before then
+/// keep their values.
+#[derive(Clone, Debug, Default)]
+pub struct Interrupter(Arc<AtomicBool>);
+
+impl Interrupter {
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// forget an interrupt no evaluation has seen yet
+    pub fn clear(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    // true once per interrupt
+    fn take(&self) -> bool {
+        self.0.load(Ordering::Relaxed) && self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         let mut env = Environment {
             globals: HashMap::new(),
             primatives: load_primatives(),
+            ..Environment::default()
         };
 
         // some Symbols bind to themselves
-        for name in vec![
+        for name in [
             "nil".to_string(),
             "t".to_string(),
             "o".to_string(),
@@ -26,15 +75,70 @@ impl Environment {
             env.globals.insert(name.clone(), Object::Symbol(name));
         }
 
+        // load is a function, (fn (name) (load name)), so that it can be
+        // passed as a value; the call inside goes to the primitive
+        let symbol = |name: &str| Object::Symbol(name.to_string());
+        env.globals.insert(
+            "load".to_string(),
+            Object::List(vec![
+                symbol("lit"),
+                symbol("clo"),
+                symbol("nil"),
+                Object::List(vec![symbol("name")]),
+                Object::List(vec![symbol("load"), symbol("name")]),
+            ]),
+        );
+
+        env
+    }
+
+    /// a new environment with the definitions in bel.bel loaded
+    #[cfg(feature = "prelude")]
+    pub fn with_prelude() -> Self {
+        let mut env = Environment::new();
+        env.load_prelude();
         env
     }
 
+    /// Evaluate bel.bel in this environment; the forms that fail are
+    /// logged as warnings (see prelude::load)
+    #[cfg(feature = "prelude")]
+    pub fn load_prelude(&mut self) -> loader::LoadReport {
+        let report = crate::prelude::load(self);
+        for form in report.failed() {
+            if let Err(err) = &form.result {
+                log::warn!("{}", err);
+            }
+        }
+        log::info!(
+            "prelude: {} of {} forms loaded",
+            report.succeeded().count(),
+            report.forms.len()
+        );
+        report
+    }
+
+    /// a handle that interrupts this environment's evaluation
+    pub fn interrupter(&self) -> Interrupter {
+        self.interrupt.clone()
+    }
+
+    /// let interrupter stop this environment's evaluation, in place of
+    /// the one it had, so one handler can serve environments in turn
+    pub fn set_interrupter(&mut self, interrupter: Interrupter) {
+        self.interrupt = interrupter;
+    }
+
     // Return an object that is reduced to its lowest terms
     pub fn evaluate(
         &mut self,
         locals: &HashMap<String, Object>,
         obj: &Object,
     ) -> Result<Object, BelError> {
+        // every step of an evaluation comes through here
+        if self.interrupt.take() {
+            return Err(BelError::Interrupted);
+        }
         let output = match obj {
             Object::Symbol(name) => self.get_bound_object(locals, name)?,
             Object::Pair(_p) => {
@@ -44,6 +148,10 @@ impl Environment {
                 if list.is_empty() {
                     return Ok(Object::Symbol("nil".to_string()));
                 }
+                // a string is a list of chars, and evaluates to itself
+                if is_string(obj) {
+                    return Ok(obj.clone());
+                }
                 // if this list starts with a symbol, it may be 'special'
                 if let Object::Symbol(name) = list[0].clone() {
                     match name.as_ref() {
@@ -59,9 +167,28 @@ impl Environment {
                         "quote" => {
                             return self.quote(&list[1..]);
                         }
+                        "bquote" => {
+                            return self.bquote(locals, &list[1..]);
+                        }
+                        "module" => {
+                            return self.module(&list[1..]);
+                        }
+                        "export" => {
+                            return self.export(&list[1..]);
+                        }
+                        "import" => {
+                            return self.import(&list[1..]);
+                        }
+                        "comma" | "comma-at" => {
+                            return Err(BelError::InvalidQuote(format!(
+                                "{} outside backquote",
+                                name
+                            )));
+                        }
                         n if self.primatives.contains_key(n) => {
+                            let prim = self.primatives[n];
                             let evaluated_list = self.evaluate_list(locals, &list[1..])?;
-                            return self.primatives[n](&evaluated_list);
+                            return prim(self, &evaluated_list);
                         }
                         _ => {
                             // if the leading symbol refers to a function,
@@ -97,8 +224,8 @@ impl Environment {
         }
         if let Object::Symbol(name) = fn_list[0].clone() {
             let evaluated_list = self.evaluate_list(&locals, &fn_list[1..])?;
-            if self.primatives.contains_key(&name) {
-                return self.primatives[&name](&evaluated_list);
+            if let Some(prim) = self.primatives.get(&name).copied() {
+                return prim(self, &evaluated_list);
             }
         } else {
             return Err(BelError::InvalidFn(
@@ -130,36 +257,214 @@ impl Environment {
         // look first in locals, then in globals
         match locals.get(name) {
             Some(obj) => Ok(obj.clone()),
-            None => match self.globals.get(name) {
-                Some(obj) => Ok(obj.clone()),
-                None => Err(BelError::UnboundSymbol(name.to_string())),
-            },
+            None => self.lookup(name),
         }
     }
 
-    fn set(&mut self, list: &[Object]) -> Result<Object, BelError> {
-        for i in 0..list.len() - 1 {
-            if let Object::Symbol(key) = list[i].clone() {
-                self.globals.insert(key, list[i + 1].clone());
-            } else {
-                return Err(BelError::InvalidObject {
-                    expected: "symbol".to_string(),
-                    found: list[i].t(),
+    /// The global value of name as seen from the current module: its own
+    /// globals, then those exported by the modules it imports, then core.
+    /// m/name is the value of name in module m, if m exports it.
+    pub fn lookup(&self, name: &str) -> Result<Object, BelError> {
+        if let Some((module_name, global)) = name.split_once('/') {
+            if let Some(module) = self.modules.get(module_name) {
+                let value = if self.current_module.as_deref() == Some(module_name) {
+                    module.globals.get(global)
+                } else {
+                    module.exported(global)
+                };
+                return value.cloned().ok_or_else(|| {
+                    BelError::Module(format!("{} does not export {}", module_name, global))
                 });
             }
         }
-        // append nil if the final arg isn't present
-        // an odd number of entries
-        // means the last value is unspecified
-        if list.len() % 2 == 1 {
-            let i = list.len() - 1;
-            if let Object::Symbol(key) = list[i].clone() {
-                self.globals.insert(key, Object::Symbol("nil".to_string()));
-            } else {
-                return Err(BelError::InvalidObject {
-                    expected: "symbol".to_string(),
-                    found: list[i].t(),
-                });
+        if let Some(module) = self.current() {
+            if let Some(obj) = module.globals.get(name) {
+                return Ok(obj.clone());
+            }
+            for import in &module.imports {
+                if let Some(obj) = self.modules.get(import).and_then(|m| m.exported(name)) {
+                    return Ok(obj.clone());
+                }
+            }
+        }
+        match self.globals.get(name) {
+            Some(obj) => Ok(obj.clone()),
+            None => Err(BelError::UnboundSymbol(name.to_string())),
+        }
+    }
+
+    /// Remove the binding of name in the current module (or core),
+    /// returning the value it had
+    pub fn unbind(&mut self, name: &str) -> Option<Object> {
+        let key = self.qualified(name);
+        self.sources.remove(&key);
+        match &self.current_module {
+            Some(module) => self.modules.get_mut(module)?.globals.remove(name),
+            None => self.globals.remove(name),
+        }
+    }
+
+    /// where the global name, as lookup would find it, was defined,
+    /// if that was in a loaded file
+    pub fn definition_source(&self, name: &str) -> Option<&Span> {
+        self.sources
+            .get(&self.qualified(name))
+            .or_else(|| self.sources.get(name))
+    }
+
+    pub(crate) fn note_definition(&mut self, name: &str, span: Span) {
+        self.sources.insert(self.qualified(name), span);
+    }
+
+    // name as seen from outside the current module, e.g. m/name
+    fn qualified(&self, name: &str) -> String {
+        match &self.current_module {
+            Some(module) if !name.contains('/') => format!("{}/{}", module, name),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Expand form for as long as it is a call of a macro, by evaluating
+    /// the body of the macro with its parameters bound to the arguments
+    /// as they are, unevaluated
+    pub fn macroexpand(&mut self, form: &Object) -> Result<Object, BelError> {
+        let mut form = form.clone();
+        while let Some(expansion) = self.macroexpand_1(&form)? {
+            form = expansion;
+        }
+        Ok(form)
+    }
+
+    // one step of macroexpand; None if form is not a macro call
+    fn macroexpand_1(&mut self, form: &Object) -> Result<Option<Object>, BelError> {
+        let list = match form {
+            Object::List(list) if !list.is_empty() => list,
+            _ => return Ok(None),
+        };
+        let macro_obj = match &list[0] {
+            Object::Symbol(name) => match self.lookup(name) {
+                Ok(obj) if obj.is_macro() => obj,
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        // (lit mac (lit clo nil p e))
+        if let Object::List(parts) = macro_obj {
+            let closure = &parts[2];
+            let locals = merge_args(closure, &list[1..])?;
+            if let Object::List(closure_parts) = closure {
+                return self.evaluate(&locals, &closure_parts[4]).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every global name lookup can find from the current module,
+    /// including m/name for what each module m exports; for completion
+    pub fn global_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.globals.keys().cloned().collect();
+        if let Some(module) = self.current() {
+            names.extend(module.globals.keys().cloned());
+            for import in &module.imports {
+                if let Some(imported) = self.modules.get(import) {
+                    names.extend(imported.exports.iter().cloned());
+                }
+            }
+        }
+        for (name, module) in &self.modules {
+            names.extend(
+                module
+                    .exports
+                    .iter()
+                    .map(|export| format!("{}/{}", name, export)),
+            );
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// the module set and def bind names in, or core
+    pub fn current_module(&self) -> &str {
+        self.current_module.as_deref().unwrap_or(CORE)
+    }
+
+    /// make name the current module, creating it if need be
+    pub fn set_current_module(&mut self, name: &str) {
+        if name == CORE {
+            self.current_module = None;
+        } else {
+            self.modules.entry(name.to_string()).or_default();
+            self.current_module = Some(name.to_string());
+        }
+    }
+
+    fn current(&self) -> Option<&Module> {
+        self.modules.get(self.current_module.as_ref()?)
+    }
+
+    // bind key in the current module
+    fn bind(&mut self, key: String, value: Object) {
+        let globals = match &self.current_module {
+            Some(name) => &mut self.modules.entry(name.clone()).or_default().globals,
+            None => &mut self.globals,
+        };
+        let old = globals.insert(key.clone(), value);
+        if let Some(undo) = &mut self.undo {
+            undo.push((self.current_module.clone(), key, old));
+        }
+    }
+
+    /// Like evaluate, but if form fails, the globals it bound before
+    /// failing get back the values they had, so a keep-going load
+    /// leaves no half-done forms behind
+    pub(crate) fn evaluate_atomically(
+        &mut self,
+        locals: &HashMap<String, Object>,
+        form: &Object,
+    ) -> Result<Object, BelError> {
+        let outer = self.undo.replace(Vec::new());
+        let result = self.evaluate(locals, form);
+        let undo = std::mem::replace(&mut self.undo, outer).unwrap_or_default();
+        if result.is_err() {
+            for (module, key, old) in undo.into_iter().rev() {
+                let globals = match module {
+                    Some(name) => &mut self.modules.entry(name).or_default().globals,
+                    None => &mut self.globals,
+                };
+                match old {
+                    Some(value) => globals.insert(key, value),
+                    None => globals.remove(&key),
+                };
+            }
+        } else if let Some(outer) = &mut self.undo {
+            // an enclosing atomic evaluation may still undo these
+            outer.extend(undo);
+        }
+        result
+    }
+
+    // When you see
+    //  (set a x b y ...)
+    // bind a to x, b to y and so on; a name without a value is bound to nil
+    fn set(&mut self, list: &[Object]) -> Result<Object, BelError> {
+        if let Some(obj) = list
+            .iter()
+            .step_by(2)
+            .find(|obj| !matches!(obj, Object::Symbol(_)))
+        {
+            return Err(BelError::InvalidObject {
+                expected: "symbol".to_string(),
+                found: obj.t(),
+            });
+        }
+        for pair in list.chunks(2) {
+            if let Object::Symbol(key) = &pair[0] {
+                let value = pair.get(1).cloned();
+                self.bind(
+                    key.clone(),
+                    value.unwrap_or(Object::Symbol("nil".to_string())),
+                );
             }
         }
         Ok(Object::Symbol("nil".to_string()))
@@ -196,6 +501,246 @@ impl Environment {
             Err(BelError::InvalidQuote(format!("{:?}", list)))
         }
     }
+
+    /// Evaluate the forms in the file the string name resolves to, in
+    /// order, and return the value of the last; the load primitive
+    pub(crate) fn load_file(&mut self, name: &Object) -> Result<Object, BelError> {
+        if !is_string(name) {
+            return Err(BelError::InvalidObject {
+                expected: "string".to_string(),
+                found: name.t(),
+            });
+        }
+        let name: String = string_chars(name).collect();
+        let path = self.resolve(&name)?;
+        loader::load(self, &path.to_string_lossy())
+    }
+
+    /// Where (load name) finds name: an absolute path is used as it is;
+    /// otherwise name is looked for relative to the file being loaded
+    /// (or the current directory), then in each directory of search_path.
+    pub fn resolve(&self, name: &str) -> Result<PathBuf, BelError> {
+        let path = Path::new(name);
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+        let base = match self.loading.last().and_then(|file| file.parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        std::iter::once(base)
+            .chain(self.search_path.iter().cloned())
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| BelError::Load(format!("{} not found", name)))
+    }
+
+    // When you see
+    //  (module m)
+    // bind names in module m from here on, until the end of the file
+    // being loaded or the next module form; (module core) goes back to
+    // the shared namespace
+    fn module(&mut self, list: &[Object]) -> Result<Object, BelError> {
+        match list {
+            [Object::Symbol(name)] if !name.contains('/') => {
+                self.set_current_module(name);
+                Ok(list[0].clone())
+            }
+            _ => Err(BelError::Module(format!(
+                "expected (module name): {:?}",
+                list
+            ))),
+        }
+    }
+
+    // When you see
+    //  (export a b ...)
+    // let other modules see a, b ... in the current module
+    fn export(&mut self, list: &[Object]) -> Result<Object, BelError> {
+        let names = symbol_names(list)?;
+        match &self.current_module {
+            Some(name) => {
+                let module = self.modules.entry(name.clone()).or_default();
+                module.exports.extend(names);
+                Ok(Object::Symbol("nil".to_string()))
+            }
+            None => Err(BelError::Module("export outside a module".to_string())),
+        }
+    }
+
+    // When you see
+    //  (import m n ...)
+    // let the current module see what modules m, n ... export
+    fn import(&mut self, list: &[Object]) -> Result<Object, BelError> {
+        let names = symbol_names(list)?;
+        if let Some(unknown) = names.iter().find(|name| !self.modules.contains_key(*name)) {
+            return Err(BelError::Module(format!("no module {}", unknown)));
+        }
+        match &self.current_module {
+            Some(name) => {
+                let module = self.modules.entry(name.clone()).or_default();
+                for import in names {
+                    if !module.imports.contains(&import) {
+                        module.imports.push(import);
+                    }
+                }
+                Ok(Object::Symbol("nil".to_string()))
+            }
+            None => Err(BelError::Module("import outside a module".to_string())),
+        }
+    }
+
+    // When you see
+    //  (bquote x)
+    // return x with every (comma e) evaluated in place and the elements
+    // of every (comma-at e) spliced into the enclosing list.
+    // Nested backquotes follow bqex in bel.bel: an inner bquote raises the
+    // depth, each comma lowers it, and only depth zero is evaluated.
+    fn bquote(
+        &mut self,
+        locals: &HashMap<String, Object>,
+        list: &[Object],
+    ) -> Result<Object, BelError> {
+        if list.len() == 1 {
+            self.bq_expand(locals, &list[0], 0)
+        } else {
+            Err(BelError::InvalidQuote(format!("{:?}", list)))
+        }
+    }
+
+    fn bq_expand(
+        &mut self,
+        locals: &HashMap<String, Object>,
+        obj: &Object,
+        depth: usize,
+    ) -> Result<Object, BelError> {
+        match obj {
+            Object::List(list) => {
+                if let Some((op, arg)) = quote_form(obj) {
+                    match (op, depth) {
+                        ("bquote", _) => return self.bq_thru(locals, op, arg, depth + 1),
+                        ("comma", 0) => return self.evaluate(locals, arg),
+                        ("comma-at", 0) => {
+                            return Err(BelError::InvalidQuote("comma-at outside list".to_string()))
+                        }
+                        ("comma", _) | ("comma-at", _) => {
+                            return self.bq_thru(locals, op, arg, depth - 1)
+                        }
+                        _ => {}
+                    }
+                }
+                let mut expanded = Vec::new();
+                for (i, item) in list.iter().enumerate() {
+                    // (a . ,b) is read as (a comma b), so a comma after the
+                    // head, with one item after it, is the list's tail
+                    if let Object::Symbol(name) = item {
+                        if i > 0 && i + 2 == list.len() && (name == "comma" || name == "comma-at") {
+                            let tail = if depth == 0 {
+                                self.evaluate(locals, &list[i + 1])?
+                            } else {
+                                self.bq_expand(locals, &Object::List(list[i..].to_vec()), depth)?
+                            };
+                            return Ok(with_tail(expanded, tail));
+                        }
+                    }
+                    match quote_form(item) {
+                        Some(("comma-at", arg)) if depth == 0 => {
+                            let spliced = self.evaluate(locals, arg)?;
+                            expanded.extend(splice_items(spliced)?);
+                        }
+                        _ => expanded.push(self.bq_expand(locals, item, depth)?),
+                    }
+                }
+                Ok(Object::List(expanded))
+            }
+            Object::Pair(pair) => {
+                let car = self.bq_expand(locals, &pair.0, depth)?;
+                let cdr = self.bq_expand(locals, &pair.1, depth)?;
+                Ok(Object::Pair(Box::new((car, cdr))))
+            }
+            _ => Ok(obj.clone()),
+        }
+    }
+
+    // rebuild (op arg) with arg expanded at the given depth; a comma-at
+    // that lands at depth zero splices into the rebuilt form, as in
+    //  `(a `(b ,,@c))
+    fn bq_thru(
+        &mut self,
+        locals: &HashMap<String, Object>,
+        op: &str,
+        arg: &Object,
+        depth: usize,
+    ) -> Result<Object, BelError> {
+        let mut rebuilt = vec![Object::Symbol(op.to_string())];
+        match quote_form(arg) {
+            Some(("comma-at", inner)) if depth == 0 => {
+                let spliced = self.evaluate(locals, inner)?;
+                rebuilt.extend(splice_items(spliced)?);
+            }
+            _ => rebuilt.push(self.bq_expand(locals, arg, depth)?),
+        }
+        Ok(Object::List(rebuilt))
+    }
+}
+
+// the names of a list of symbols
+fn symbol_names(list: &[Object]) -> Result<Vec<String>, BelError> {
+    list.iter()
+        .map(|obj| match obj {
+            Object::Symbol(name) => Ok(name.to_string()),
+            _ => Err(BelError::InvalidObject {
+                expected: "symbol".to_string(),
+                found: obj.t(),
+            }),
+        })
+        .collect()
+}
+
+// if obj is one of the two element quote forms, e.g. (comma x),
+// return the operator name and its argument
+fn quote_form(obj: &Object) -> Option<(&str, &Object)> {
+    if let Object::List(list) = obj {
+        if list.len() == 2 {
+            if let Object::Symbol(name) = &list[0] {
+                match name.as_str() {
+                    "quote" | "bquote" | "comma" | "comma-at" => {
+                        return Some((name.as_str(), &list[1]))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    None
+}
+
+// the value of a comma-at must be a list whose elements are spliced in
+fn splice_items(obj: Object) -> Result<Vec<Object>, BelError> {
+    match obj {
+        Object::List(items) => Ok(items),
+        _ if obj.is_nil() => Ok(vec![]),
+        _ => Err(BelError::InvalidObject {
+            expected: "list".to_string(),
+            found: obj.t(),
+        }),
+    }
+}
+
+// the list of items whose last cdr is tail, as the parser builds
+// (a b . c)
+fn with_tail(mut items: Vec<Object>, tail: Object) -> Object {
+    match tail {
+        Object::List(tail) => {
+            items.extend(tail);
+            Object::List(items)
+        }
+        _ if tail.is_nil() => Object::List(items),
+        _ => items
+            .into_iter()
+            .rev()
+            .fold(tail, |cdr, car| Object::Pair(Box::new((car, cdr)))),
+    }
 }
 
 fn define_closure(list: &[Object]) -> Result<(Object, Object), BelError> {
@@ -321,7 +866,7 @@ mod tests {
     #[test]
     fn some_objects_evaluate_to_themselves() -> Result<(), BelError> {
         let mut env = Environment::new();
-        for obj in vec![
+        for obj in [
             Object::Symbol("nil".to_string()),
             Object::Symbol("t".to_string()),
             Object::Symbol("o".to_string()),
@@ -475,4 +1020,165 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn bquote_without_commas_is_quote() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        let parse_obj = parser.parse("`(a b)")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a b)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bquote_evaluates_commas() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        let parse_obj = parser.parse("(set b x c (y z))")?;
+        env.evaluate(&locals, &parse_obj)?;
+
+        let parse_obj = parser.parse("`(a ,b ,@c d)")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a x y z d)")?);
+
+        let parse_obj = parser.parse("`(a ,@nil)")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a)")?);
+
+        // a comma after the dot makes the tail
+        let parse_obj = parser.parse("`(a . ,c)")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a y z)")?);
+
+        let parse_obj = parser.parse("`(a . ,b)")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a . x)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_bquote_only_expands_outer_commas() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        let parse_obj = parser.parse("(set x v)")?;
+        env.evaluate(&locals, &parse_obj)?;
+
+        let parse_obj = parser.parse("`(a `(b ,c ,,x))")?;
+        let obj = env.evaluate(&locals, &parse_obj)?;
+        assert_eq!(obj, parser.parse("(a `(b ,c ,v))")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn comma_outside_bquote_is_an_error() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        let parse_obj = parser.parse(",a")?;
+        assert!(env.evaluate(&locals, &parse_obj).is_err());
+
+        let parse_obj = parser.parse("`,@a")?;
+        assert!(env.evaluate(&locals, &parse_obj).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn modules_keep_their_globals_apart() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+        let mut eval = |env: &mut Environment, text: &str| {
+            let obj = parser.parse(text)?;
+            env.evaluate(&locals, &obj)
+        };
+
+        eval(&mut env, "(set parse core-parse)")?;
+        eval(&mut env, "(module json)")?;
+        eval(&mut env, "(set parse json-parse helper h)")?;
+        eval(&mut env, "(export parse)")?;
+        assert_eq!(eval(&mut env, "parse")?.to_string(), "json-parse");
+        assert_eq!(eval(&mut env, "json/helper")?.to_string(), "h");
+
+        eval(&mut env, "(module app)")?;
+        assert_eq!(env.current_module(), "app");
+        assert_eq!(eval(&mut env, "parse")?.to_string(), "core-parse");
+        assert_eq!(eval(&mut env, "json/parse")?.to_string(), "json-parse");
+        assert!(eval(&mut env, "json/helper").is_err());
+        eval(&mut env, "(import json)")?;
+        assert_eq!(eval(&mut env, "parse")?.to_string(), "json-parse");
+        assert!(eval(&mut env, "helper").is_err());
+        assert!(eval(&mut env, "(import xml)").is_err());
+
+        eval(&mut env, "(module core)")?;
+        assert_eq!(eval(&mut env, "parse")?.to_string(), "core-parse");
+        assert!(eval(&mut env, "(export parse)").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn macroexpand_expands_macro_calls_only() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        let parse_obj = parser.parse("(mac twice (x) `(do ,x ,x))")?;
+        env.evaluate(&locals, &parse_obj)?;
+        let parse_obj = parser.parse("(mac again (x) `(twice ,x))")?;
+        env.evaluate(&locals, &parse_obj)?;
+
+        let obj = env.macroexpand(&parser.parse("(again (f a))")?)?;
+        assert_eq!(obj, parser.parse("(do (f a) (f a))")?);
+        let obj = env.macroexpand(&parser.parse("(f (twice a))")?)?;
+        assert_eq!(obj, parser.parse("(f (twice a))")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_unbind_globals() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+
+        env.evaluate(&locals, &parser.parse("(set a b)")?)?;
+        assert_eq!(env.unbind("a"), Some(Object::Symbol("b".to_string())));
+        assert!(env.lookup("a").is_err());
+        assert_eq!(env.unbind("a"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn an_interrupt_stops_one_evaluation() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        let locals: HashMap<String, Object> = HashMap::new();
+        env.evaluate(&locals, &parser.parse("(set a b)")?)?;
+
+        let interrupter = env.interrupter();
+        std::thread::spawn(move || interrupter.interrupt())
+            .join()
+            .unwrap();
+        let err = env
+            .evaluate(&locals, &parser.parse("(set c a)")?)
+            .unwrap_err();
+        assert!(err.is_interrupted());
+        assert_eq!(env.lookup("a")?, Object::Symbol("b".to_string()));
+        env.evaluate(&locals, &parser.parse("a")?)?;
+
+        Ok(())
+    }
 }
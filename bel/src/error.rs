@@ -1,6 +1,8 @@
 use std::io;
 use thiserror::Error;
 
+use crate::source::Span;
+
 #[derive(Error, Debug)]
 pub enum BelError {
     #[error("not implemented: {0}")]
@@ -9,6 +11,9 @@ pub enum BelError {
     #[error("parse error: {0}")]
     ParseError(String),
 
+    #[error("incomplete input: {0}")]
+    Incomplete(String),
+
     #[error("unbound symbol: {0}")]
     UnboundSymbol(String),
 
@@ -24,6 +29,53 @@ pub enum BelError {
     #[error("invalid fn: {0}")]
     InvalidFn(String),
 
+    #[error("load error: {0}")]
+    Load(String),
+
+    #[error("module error: {0}")]
+    Module(String),
+
+    #[error("invalid image: {0}")]
+    InvalidImage(String),
+
+    #[error("interrupted")]
+    Interrupted,
+
     #[error("io error {0}")]
     Io(#[from] io::Error),
+
+    #[error("{location}: {error}")]
+    Located {
+        location: Span,
+        error: Box<BelError>,
+    },
+}
+
+impl BelError {
+    /// where in the source the error happened, if known
+    pub fn location(&self) -> Option<&Span> {
+        match self {
+            BelError::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// true if the input ended in the middle of a form,
+    /// so that reading more input might succeed
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            BelError::Incomplete(_) => true,
+            BelError::Located { error, .. } => error.is_incomplete(),
+            _ => false,
+        }
+    }
+
+    /// true if the evaluation was stopped by an Interrupter
+    pub fn is_interrupted(&self) -> bool {
+        match self {
+            BelError::Interrupted => true,
+            BelError::Located { error, .. } => error.is_interrupted(),
+            _ => false,
+        }
+    }
 }
@@ -2,6 +2,7 @@ use crate::error::BelError;
 use crate::object::Object;
 use std::collections::HashMap;
 
+#[allow(dead_code)]
 pub trait Function {
     fn apply(
         &self,
@@ -0,0 +1,345 @@
+//! Images: an Environment's globals saved to a file, so that a session
+//! can start without evaluating bel.bel again.
+//!
+//! An image is the magic bytes RBELIMG, the format version and the
+//! crate version it was written by, then the core globals, each as its
+//! name and value, then each module with its exports, imports and
+//! globals. Each value is written out whole, as a tree. Last come the
+//! search path and where loaded files defined globals; what is being
+//! loaded and the current module are not saved, as a session starts
+//! from an image between loads.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use crate::environment::Environment;
+use crate::error::BelError;
+use crate::module::Module;
+use crate::object::Object;
+use crate::source::{Position, Span};
+
+const MAGIC: &[u8; 7] = b"RBELIMG";
+
+/// the version of the image format; images in other formats are rejected
+pub const FORMAT_VERSION: u32 = 3;
+
+// object tags
+const SYMBOL: u8 = 0;
+const PAIR: u8 = 1;
+const LIST: u8 = 2;
+const CHAR: u8 = 3;
+const STREAM: u8 = 4;
+
+/// write the globals of env to out as an image
+pub fn save<W: Write>(env: &Environment, out: W) -> Result<(), BelError> {
+    let mut out = BufWriter::new(out);
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    write_str(&mut out, env!("CARGO_PKG_VERSION"))?;
+
+    write_globals(&mut out, &env.globals)?;
+    write_len(&mut out, env.modules.len())?;
+    for name in sorted(env.modules.keys()) {
+        let module = &env.modules[name];
+        write_str(&mut out, name)?;
+        write_strs(&mut out, sorted(module.exports.iter()))?;
+        write_strs(&mut out, module.imports.iter().collect())?;
+        write_globals(&mut out, &module.globals)?;
+    }
+    // paths that are not UTF-8 are saved as near as a str can say them
+    let paths: Vec<String> = env
+        .search_path
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    write_strs(&mut out, paths.iter().collect())?;
+    write_len(&mut out, env.sources.len())?;
+    for name in sorted(env.sources.keys()) {
+        write_str(&mut out, name)?;
+        write_span(&mut out, &env.sources[name])?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// a new environment with the globals in the image read from input
+pub fn restore<R: Read>(input: R) -> Result<Environment, BelError> {
+    let mut input = BufReader::new(input);
+    let mut magic = [0; 7];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(BelError::InvalidImage("not an image".to_string()));
+    }
+    let version = read_u32(&mut input)?;
+    let crate_version = read_str(&mut input)?;
+    if version != FORMAT_VERSION || crate_version != env!("CARGO_PKG_VERSION") {
+        return Err(BelError::InvalidImage(format!(
+            "written by version {} (format {}) of this crate, not {} (format {})",
+            crate_version,
+            version,
+            env!("CARGO_PKG_VERSION"),
+            FORMAT_VERSION
+        )));
+    }
+
+    let mut env = Environment::new();
+    env.globals.extend(read_globals(&mut input)?);
+    for _ in 0..read_u32(&mut input)? {
+        let name = read_str(&mut input)?;
+        let module = Module {
+            exports: read_strs(&mut input)?.into_iter().collect(),
+            imports: read_strs(&mut input)?,
+            globals: read_globals(&mut input)?,
+        };
+        env.modules.insert(name, module);
+    }
+    env.search_path = read_strs(&mut input)?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    for _ in 0..read_u32(&mut input)? {
+        let name = read_str(&mut input)?;
+        env.sources.insert(name, read_span(&mut input)?);
+    }
+    Ok(env)
+}
+
+/// save env as an image in the file at path
+pub fn save_file(env: &Environment, path: &str) -> Result<(), BelError> {
+    save(env, File::create(path)?)
+}
+
+/// restore an environment from the image in the file at path
+pub fn restore_file(path: &str) -> Result<Environment, BelError> {
+    restore(File::open(path)?)
+}
+
+fn write_len<W: Write>(out: &mut W, len: usize) -> Result<(), BelError> {
+    let len = u32::try_from(len)
+        .map_err(|_| BelError::InvalidImage(format!("too long to save: {}", len)))?;
+    out.write_all(&len.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_str<W: Write>(out: &mut W, s: &str) -> Result<(), BelError> {
+    write_len(out, s.len())?;
+    out.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn write_strs<W: Write>(out: &mut W, strs: Vec<&String>) -> Result<(), BelError> {
+    write_len(out, strs.len())?;
+    strs.iter().try_for_each(|s| write_str(out, s))
+}
+
+// sorted, so that the same globals always make the same image
+fn sorted<'a>(names: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+    let mut names: Vec<&String> = names.collect();
+    names.sort();
+    names
+}
+
+fn write_globals<W: Write>(out: &mut W, globals: &HashMap<String, Object>) -> Result<(), BelError> {
+    write_len(out, globals.len())?;
+    for name in sorted(globals.keys()) {
+        write_str(out, name)?;
+        write_object(out, &globals[name])?;
+    }
+    Ok(())
+}
+
+fn write_object<W: Write>(out: &mut W, obj: &Object) -> Result<(), BelError> {
+    match obj {
+        Object::Symbol(name) => {
+            out.write_all(&[SYMBOL])?;
+            write_str(out, name)
+        }
+        Object::Pair(pair) => {
+            out.write_all(&[PAIR])?;
+            write_object(out, &pair.0)?;
+            write_object(out, &pair.1)
+        }
+        Object::List(list) => {
+            out.write_all(&[LIST])?;
+            write_len(out, list.len())?;
+            list.iter().try_for_each(|item| write_object(out, item))
+        }
+        Object::Char(c) => {
+            out.write_all(&[CHAR])?;
+            write_str(out, c)
+        }
+        Object::Stream => Ok(out.write_all(&[STREAM])?),
+    }
+}
+
+// the file, if any, after a 0 for none or a 1, then the positions
+fn write_span<W: Write>(out: &mut W, span: &Span) -> Result<(), BelError> {
+    match &span.file {
+        Some(file) => {
+            out.write_all(&[1])?;
+            write_str(out, file)?;
+        }
+        None => out.write_all(&[0])?,
+    }
+    for n in [
+        span.start.line,
+        span.start.column,
+        span.end.line,
+        span.end.column,
+    ] {
+        write_len(out, n)?;
+    }
+    Ok(())
+}
+
+fn read_u32<R: Read>(input: &mut R) -> Result<u32, BelError> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_str<R: Read>(input: &mut R) -> Result<String, BelError> {
+    let len = read_u32(input)? as u64;
+    let mut s = String::new();
+    input.take(len).read_to_string(&mut s)?;
+    if s.len() as u64 != len {
+        return Err(BelError::InvalidImage("truncated".to_string()));
+    }
+    Ok(s)
+}
+
+fn read_strs<R: Read>(input: &mut R) -> Result<Vec<String>, BelError> {
+    (0..read_u32(input)?).map(|_| read_str(input)).collect()
+}
+
+fn read_globals<R: Read>(input: &mut R) -> Result<HashMap<String, Object>, BelError> {
+    let mut globals = HashMap::new();
+    for _ in 0..read_u32(input)? {
+        let name = read_str(input)?;
+        let value = read_object(input)?;
+        globals.insert(name, value);
+    }
+    Ok(globals)
+}
+
+fn read_span<R: Read>(input: &mut R) -> Result<Span, BelError> {
+    let mut tag = [0];
+    input.read_exact(&mut tag)?;
+    let file = match tag[0] {
+        0 => None,
+        1 => Some(read_str(input)?),
+        tag => return Err(BelError::InvalidImage(format!("unknown file tag {}", tag))),
+    };
+    let mut n = || read_u32(input).map(|n| n as usize);
+    let start = Position::new(n()?, n()?);
+    let end = Position::new(n()?, n()?);
+    Ok(Span::new(file, start, end))
+}
+
+fn read_object<R: Read>(input: &mut R) -> Result<Object, BelError> {
+    let mut tag = [0];
+    input.read_exact(&mut tag)?;
+    match tag[0] {
+        SYMBOL => Ok(Object::Symbol(read_str(input)?)),
+        PAIR => {
+            let car = read_object(input)?;
+            let cdr = read_object(input)?;
+            Ok(Object::Pair(Box::new((car, cdr))))
+        }
+        LIST => {
+            let len = read_u32(input)?;
+            let mut list = Vec::new();
+            for _ in 0..len {
+                list.push(read_object(input)?);
+            }
+            Ok(Object::List(list))
+        }
+        CHAR => Ok(Object::Char(read_str(input)?)),
+        STREAM => Ok(Object::Stream),
+        tag => Err(BelError::InvalidImage(format!("unknown tag {}", tag))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn restored_globals_equal_saved_ones() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let mut env = Environment::new();
+        env.globals.insert(
+            "f".to_string(),
+            parser.parse("(lit clo nil (x) (id x \"é\\n\"))")?,
+        );
+        env.globals
+            .insert("p".to_string(), parser.parse("(a b . c)")?);
+        env.globals.insert("s".to_string(), Object::Stream);
+
+        let mut image = Vec::new();
+        save(&env, &mut image)?;
+        let restored = restore(image.as_slice())?;
+        assert_eq!(restored.globals, env.globals);
+
+        Ok(())
+    }
+
+    #[test]
+    fn images_keep_modules() -> Result<(), BelError> {
+        let mut env = Environment::new();
+        crate::loader::load_str(
+            &mut env,
+            "(module a) (set x y) (export x)\n(module b) (import a) (set z w)",
+            "modules",
+        )?;
+
+        let mut image = Vec::new();
+        save(&env, &mut image)?;
+        let restored = restore(image.as_slice())?;
+        assert_eq!(restored.modules, env.modules);
+        assert_eq!(restored.lookup("a/x")?.to_string(), "y");
+
+        Ok(())
+    }
+
+    #[test]
+    fn images_keep_the_search_path_and_sources() -> Result<(), BelError> {
+        let mut env = Environment::new();
+        crate::loader::load_str(&mut env, "(set a b)\n(def f (x) x)", "defs.bel")?;
+        env.search_path.push(PathBuf::from("/usr/share/bel"));
+
+        let mut image = Vec::new();
+        save(&env, &mut image)?;
+        let restored = restore(image.as_slice())?;
+        assert_eq!(restored.search_path, env.search_path);
+        assert_eq!(restored.sources, env.sources);
+        assert_eq!(
+            restored.definition_source("f").unwrap().to_string(),
+            "defs.bel:2:1"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stale_and_damaged_images_are_rejected() -> Result<(), BelError> {
+        let mut image = Vec::new();
+        save(&Environment::new(), &mut image)?;
+
+        let mut stale = image.clone();
+        stale[7] += 1;
+        let err = restore(stale.as_slice()).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("invalid image: written by version"));
+
+        let err = restore(&b"(set a b)"[..]).err().unwrap();
+        assert_eq!(err.to_string(), "invalid image: not an image");
+        assert!(restore(&image[..image.len() - 1]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,7 +1,14 @@
 pub mod environment;
 pub mod error;
 mod function;
+pub mod image;
 pub mod loader;
+pub mod module;
 pub mod object;
 pub mod parser;
+#[cfg(feature = "prelude")]
+pub mod prelude;
 mod primatives;
+pub mod printer;
+pub mod reader;
+pub mod source;
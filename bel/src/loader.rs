@@ -1,37 +1,419 @@
 use crate::environment;
-use crate::object;
-use crate::parser;
-use anyhow::{Context, Result};
+use crate::error::BelError;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::reader::Reader;
+use crate::source::Span;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn load(env: &mut environment::Environment, filepath: &str) -> Result<()> {
-    let file = File::open(filepath)?;
-    let reader = BufReader::new(file);
-    let mut parser = parser::Parser::new();
-
-    let mut accum = String::new();
-    'line_loop: for line in reader.lines() {
-        let line = line?;
-        // https://sep.yimg.com/ty/cdn/paulgraham/bellanguage.txt has some weird
-        // unicode byte order mark
-        if line.starts_with('\u{feff}') {
-            continue 'line_loop;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Reads the forms in the file at filepath and evaluates each in turn,
+/// returning the value of the last (nil for an empty file). The first
+/// form that fails to read or evaluate stops the load, with an error
+/// that says where it is.
+pub fn load(env: &mut environment::Environment, filepath: &str) -> Result<Object, BelError> {
+    let path = loadable_path(env, filepath)?;
+    let file = File::open(&path)?;
+    env.loading.push(path);
+    let result = load_read(env, file, filepath);
+    env.loading.pop();
+    result
+}
+
+// the canonical path of filepath, which (load name) in the file will
+// look next to, unless env is already loading it
+fn loadable_path(env: &environment::Environment, filepath: &str) -> Result<PathBuf, BelError> {
+    // a file that loads itself, directly or not, would never finish
+    let path = fs::canonicalize(filepath)?;
+    if env.loading.contains(&path) {
+        return Err(BelError::Load(format!(
+            "{} is already being loaded",
+            filepath
+        )));
+    }
+    Ok(path)
+}
+
+/// Like load, for Bel text from any source; errors name it name
+pub fn load_read<R: Read>(
+    env: &mut environment::Environment,
+    input: R,
+    name: &str,
+) -> Result<Object, BelError> {
+    let mut reader = Reader::for_file(BufReader::new(input), name);
+    // a (module m) form lasts to the end of the text it is in
+    let module = env.current_module().to_string();
+    let result = evaluate_all(env, &mut reader);
+    env.set_current_module(&module);
+    result
+}
+
+fn evaluate_all<R: BufRead>(
+    env: &mut environment::Environment,
+    reader: &mut Reader<R>,
+) -> Result<Object, BelError> {
+    let locals: HashMap<String, Object> = HashMap::new();
+    let mut value = Object::Symbol("nil".to_string());
+    while let Some(form) = reader.next() {
+        let form = form?;
+        value = env
+            .evaluate(&locals, &form)
+            .map_err(|err| match reader.source_map() {
+                Some(source_map) => source_map.locate(&form, err),
+                None => err,
+            })?;
+        if let (Some(name), Some(source_map)) = (defined_name(&form), reader.source_map()) {
+            env.note_definition(&name, source_map.span.clone());
+        }
+    }
+
+    Ok(value)
+}
+
+/// Like load, for Bel text in memory, such as a source compiled into
+/// the program with include_str!:
+///
+/// ```
+/// use bel::environment::Environment;
+/// use bel::loader;
+///
+/// let mut env = Environment::new();
+/// let value = loader::load_str(&mut env, "(set a b)\n'a", "example").unwrap();
+/// assert_eq!(value.to_string(), "a");
+/// ```
+pub fn load_str(
+    env: &mut environment::Environment,
+    text: &str,
+    name: &str,
+) -> Result<Object, BelError> {
+    load_read(env, text.as_bytes(), name)
+}
+
+/// How one top-level form fared in a keep-going load
+#[derive(Debug)]
+pub struct FormReport {
+    pub location: Option<Span>,
+    /// what the form defines, for def, mac and set forms
+    pub name: Option<String>,
+    /// the value of the form, or why it could not be read or evaluated
+    pub result: Result<Object, BelError>,
+    pub elapsed: Duration,
+}
+
+/// Every top-level form of a file loaded by load_report, in order
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub forms: Vec<FormReport>,
+}
+
+impl LoadReport {
+    pub fn succeeded(&self) -> impl Iterator<Item = &FormReport> {
+        self.forms.iter().filter(|form| form.result.is_ok())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &FormReport> {
+        self.forms.iter().filter(|form| form.result.is_err())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.forms.iter().map(|form| form.elapsed).sum()
+    }
+
+    /// true if an interrupt stopped the load before the end
+    pub fn interrupted(&self) -> bool {
+        self.failed().any(|form| match &form.result {
+            Err(err) => err.is_interrupted(),
+            Ok(_) => false,
+        })
+    }
+}
+
+// one line per failure, then a count
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for form in self.failed() {
+            if let Err(err) = &form.result {
+                match &form.name {
+                    Some(name) => writeln!(f, "{} ({})", err, name)?,
+                    None => writeln!(f, "{}", err)?,
+                }
+            }
+        }
+        write!(
+            f,
+            "{} of {} forms loaded in {:.3}s",
+            self.succeeded().count(),
+            self.forms.len(),
+            self.elapsed().as_secs_f64()
+        )
+    }
+}
+
+/// Like load, but carries on past forms that fail to read or evaluate,
+/// and reports how each form fared and how long it took.
+/// Only failing to read the file at all is an error; an interrupt
+/// stops the load, and is the last form reported.
+pub fn load_report(
+    env: &mut environment::Environment,
+    filepath: &str,
+) -> Result<LoadReport, BelError> {
+    let path = loadable_path(env, filepath)?;
+    let text = fs::read_to_string(&path)?;
+    env.loading.push(path);
+    let report = load_report_str(env, &text, filepath);
+    env.loading.pop();
+    Ok(report)
+}
+
+/// Like load_report, for Bel text in memory; errors name it name
+pub fn load_report_str(env: &mut environment::Environment, text: &str, name: &str) -> LoadReport {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut parser = Parser::for_file(name);
+    let (forms, errors) = parser.parse_recovering(text);
+    let locals: HashMap<String, Object> = HashMap::new();
+    let module = env.current_module().to_string();
+
+    let mut report = LoadReport::default();
+    let source_maps = parser.source_map().map(|map| map.children.clone());
+    for (i, form) in forms.iter().enumerate() {
+        let source_map = source_maps.as_ref().and_then(|maps| maps.get(i));
+        let start = Instant::now();
+        let result = env.evaluate_atomically(&locals, form);
+        let elapsed = start.elapsed();
+        if let (Some(name), Some(source_map), Ok(_)) = (defined_name(form), source_map, &result) {
+            env.note_definition(&name, source_map.span.clone());
         }
-        if line.starts_with(';') {
-            continue 'line_loop;
+        let interrupted = matches!(&result, Err(err) if err.is_interrupted());
+        report.forms.push(FormReport {
+            location: source_map.map(|map| map.span.clone()),
+            name: defined_name(form),
+            result: match source_map {
+                Some(source_map) => result.map_err(|err| source_map.locate(form, err)),
+                None => result,
+            },
+            elapsed,
+        });
+        if interrupted {
+            env.set_current_module(&module);
+            return report;
         }
-        if line.is_empty() && !accum.is_empty() {
-            let parsed_expr = parser.parse(&accum)?;
-            let locals: HashMap<String, object::Object> = HashMap::new();
-            env.evaluate(&locals, &parsed_expr)
-                .context(format!("\n\n{}\n", accum))?;
-            accum.clear();
+    }
+    for err in errors {
+        report.forms.push(FormReport {
+            location: err.location().cloned(),
+            name: None,
+            result: Err(err),
+            elapsed: Duration::ZERO,
+        });
+    }
+    env.set_current_module(&module);
+    // forms that could not be read go where they were in the file
+    report
+        .forms
+        .sort_by_key(|form| form.location.as_ref().map(|span| span.start.line));
+
+    report
+}
+
+// the name in (def name ...), (mac name ...) or (set name ...)
+fn defined_name(form: &Object) -> Option<String> {
+    if let Object::List(list) = form {
+        if let [Object::Symbol(op), Object::Symbol(name), ..] = list.as_slice() {
+            if op == "def" || op == "mac" || op == "set" {
+                return Some(name.to_string());
+            }
         }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // a path no other test run is using
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+    }
+
+    fn load_text(name: &str, text: &str) -> Result<Object, BelError> {
+        let path = temp_path(name);
+        fs::write(&path, text)?;
+        let mut env = environment::Environment::new();
+        let result = load(&mut env, path.to_str().unwrap());
+        fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn loads_forms_however_they_are_laid_out() -> Result<(), BelError> {
+        let value = load_text(
+            "loader-layout.bel",
+            "\u{feff}; a comment\n(set a 'b) 'c\n(quote\n\n  d)\n'e",
+        )?;
+        assert_eq!(value, Object::Symbol("e".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_where_a_form_failed() {
+        let err = load_text("loader-fail.bel", "(set a 'b)\n\n`(c\n  ,x)").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("loader-fail.bel:4:4: unbound symbol: x"));
+
+        let err = load_text("loader-incomplete.bel", "(set a 'b)\n(car").unwrap_err();
+        assert!(err.is_incomplete());
+    }
+
+    #[test]
+    fn loads_from_strings_and_readers() -> Result<(), BelError> {
+        let mut env = environment::Environment::new();
+        assert_eq!(load_str(&mut env, "", "empty")?.to_string(), "nil");
+        assert_eq!(load_str(&mut env, "(set a b) 'c", "text")?.to_string(), "c");
+        assert_eq!(env.globals.get("a"), Some(&Object::Symbol("b".to_string())));
+
+        let err = load_read(&mut env, "\n  x".as_bytes(), "input").unwrap_err();
+        assert_eq!(err.to_string(), "input:2:3: unbound symbol: x");
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_report_carries_on_past_failures() -> Result<(), BelError> {
+        let path = temp_path("loader-report.bel");
+        fs::write(&path, "(set a 'b)\n\n(set c\n  (d e)\n\n(set f g)\n(h)\n")?;
+        let mut env = environment::Environment::new();
+        let report = load_report(&mut env, path.to_str().unwrap())?;
+        fs::remove_file(&path)?;
+
+        let names: Vec<Option<&str>> = report.forms.iter().map(|f| f.name.as_deref()).collect();
+        assert_eq!(names, [Some("a"), None, Some("f"), None]);
+        let failed: Vec<usize> = report
+            .failed()
+            .map(|f| f.location.as_ref().unwrap().start.line)
+            .collect();
+        assert_eq!(failed, [3, 7]);
+        assert!(report.to_string().ends_with(&format!(
+            "2 of 4 forms loaded in {:.3}s",
+            report.elapsed().as_secs_f64()
+        )));
+        assert_eq!(env.globals.get("f"), Some(&Object::Symbol("g".to_string())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_report_undoes_a_failed_form() {
+        let mut env = environment::Environment::new();
+        let report = load_report_str(&mut env, "(set a x)\n((set a y b z) (c))\n", "undo.bel");
+
+        assert_eq!(report.failed().count(), 1);
+        assert_eq!(env.globals.get("a"), Some(&Object::Symbol("x".to_string())));
+        assert_eq!(env.globals.get("b"), None);
+    }
+
+    #[test]
+    fn an_interrupt_stops_load_report() {
+        let mut env = environment::Environment::new();
+        env.interrupter().interrupt();
+        let report = load_report_str(&mut env, "(set a 'b)\n(set c 'd)\n(e", "text");
+        assert_eq!(report.forms.len(), 1);
+        assert!(report.interrupted());
+        assert!(!env.globals.contains_key("c"));
+    }
+
+    #[test]
+    fn bel_code_can_load_files() -> Result<(), BelError> {
+        let dir = temp_path("loader-load");
+        fs::create_dir_all(dir.join("lib"))?;
+        fs::write(dir.join("main.bel"), "(load \"lib/util.bel\")")?;
+        fs::write(
+            dir.join("lib/util.bel"),
+            "(load \"more.bel\")\n(set util t)",
+        )?;
+        fs::write(dir.join("lib/more.bel"), "(set more t)")?;
+        fs::write(dir.join("lib/self.bel"), "(load \"self.bel\")")?;
+
+        let mut env = environment::Environment::new();
+        load(&mut env, dir.join("main.bel").to_str().unwrap())?;
+        assert!(env.globals.contains_key("util"));
+        assert!(env.globals.contains_key("more"));
+
+        let mut env = environment::Environment::new();
+        let err = load_str(&mut env, "(load \"more.bel\")", "text").unwrap_err();
+        assert_eq!(err.to_string(), "text:1:1: load error: more.bel not found");
+        env.search_path.push(dir.join("lib"));
+        load_str(&mut env, "(load \"more.bel\")", "text")?;
+        assert!(env.globals.contains_key("more"));
+
+        // load evaluates its argument, and is a value like any function
+        load_str(&mut env, "(set util \"util.bel\")\n(load util)", "text")?;
+        let load = env.lookup("load")?;
+        assert!(load.is_function());
+        env.globals.insert("f".to_string(), load);
+        env.globals.remove("more");
+        load_str(&mut env, "(f \"more.bel\")", "text")?;
+        assert!(env.globals.contains_key("more"));
 
-        accum.push_str(&format!("{}\n", &line));
+        let err = load_str(&mut env, "(load \"self.bel\")", "text").unwrap_err();
+        assert!(err.to_string().contains("self.bel is already being loaded"));
+        assert!(env.loading.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn load_report_loads_files_next_to_it() -> Result<(), BelError> {
+        let dir = temp_path("loader-report-load");
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("main.bel"),
+            "(load \"util.bel\")\n(load \"main.bel\")",
+        )?;
+        fs::write(dir.join("util.bel"), "(set util t)")?;
+
+        let mut env = environment::Environment::new();
+        let report = load_report(&mut env, dir.join("main.bel").to_str().unwrap())?;
+        fs::remove_dir_all(&dir)?;
+
+        assert!(env.globals.contains_key("util"));
+        let failed: Vec<String> = report
+            .failed()
+            .map(|f| f.result.as_ref().unwrap_err().to_string())
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].contains("main.bel is already being loaded"));
+        assert!(env.loading.is_empty());
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn records_where_globals_were_defined() -> Result<(), BelError> {
+        let mut env = environment::Environment::new();
+        load_str(&mut env, "(set a b)\n\n  (def f (x) x)", "defs.bel")?;
+        let span = env.definition_source("f").unwrap();
+        assert_eq!(span.to_string(), "defs.bel:3:3");
+        assert!(env.definition_source("b").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn a_module_form_lasts_to_the_end_of_its_file() -> Result<(), BelError> {
+        let mut env = environment::Environment::new();
+        load_str(&mut env, "(module m)\n(set a b)", "m.bel")?;
+        assert_eq!(env.current_module(), "core");
+        assert!(env.modules["m"].globals.contains_key("a"));
+        assert!(!env.globals.contains_key("a"));
+
+        Ok(())
+    }
 }
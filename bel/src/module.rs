@@ -0,0 +1,33 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::object::Object;
+
+/// the name of the shared namespace, Environment::globals,
+/// which holds the definitions in bel.bel
+pub const CORE: &str = "core";
+
+/// A namespace of globals, declared in Bel with (module name).
+/// Code in a module sees its own globals, then the exports of the
+/// modules it imports, then core; other code can refer to an exported
+/// global as name/global.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub globals: HashMap<String, Object>,
+    pub exports: HashSet<String>,
+    pub imports: Vec<String>,
+}
+
+impl Module {
+    pub fn new() -> Module {
+        Module::default()
+    }
+
+    /// the value of name if this module exports it
+    pub fn exported(&self, name: &str) -> Option<&Object> {
+        if self.exports.contains(name) {
+            self.globals.get(name)
+        } else {
+            None
+        }
+    }
+}
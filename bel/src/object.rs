@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::printer;
+
 /// Bel has four fundamental data types:
 /// symbols, pairs, characters, and streams.
 /// Instances of the four fundamental types are called objects
@@ -78,17 +80,27 @@ impl Object {
 
         false
     }
+
+    pub fn is_macro(&self) -> bool {
+        if let Object::List(list) = self {
+            //  (set n (lit mac (lit clo nil p e)))
+            if list.len() != 3 {
+                return false;
+            }
+            if !list[0].is_literal() {
+                return false;
+            }
+            if let Object::Symbol(name) = &list[1] {
+                return name == "mac" && list[2].is_function();
+            }
+        }
+
+        false
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use self::Object::*;
-        match &self {
-            Symbol(word) => write!(f, "{}", word),
-            Pair(pair) => write!(f, "({} . {})", pair.0, pair.1),
-            List(l) => write!(f, "{:?}", l),
-            Char(c) => write!(f, "\\{}", c),
-            Stream => write!(f, "<stream>"),
-        }
+        write!(f, "{}", printer::print(self))
     }
 }
@@ -1,27 +1,63 @@
+use std::collections::VecDeque;
 use std::default;
 
 use crate::error::BelError;
 use crate::object::Object;
+use crate::source::{Position, SourceMap, Span};
 
+#[derive(Clone, Copy)]
 enum State {
     ConsumeWhitespace,
     BuildSymbol,
     BuildChar,
+    // text between delimiters: " for strings, ¦ for symbols
+    BuildString(char),
+    StringEscape(char),
+    Comment,
 }
 
-enum QuotingState {
-    None,
-    Starting,
-    Atom,
-    List,
+// one level of list being built
+struct Frame {
+    list: Vec<Object>,
+    // where each object in list came from
+    sources: Vec<SourceMap>,
+    // quote prefixes (' ` , ,@) waiting for the next complete object
+    prefixes: Vec<(&'static str, Span)>,
+    // the opening bracket, None for the outer list
+    open: Option<char>,
+    start: Position,
+    // the number of objects seen before a dot, e.g. 2 for (a b . c)
+    dot: Option<usize>,
+}
+
+impl Frame {
+    fn new(open: Option<char>, start: Position) -> Frame {
+        Frame {
+            list: Vec::new(),
+            sources: Vec::new(),
+            prefixes: Vec::new(),
+            open,
+            start,
+            dot: None,
+        }
+    }
 }
 
 pub struct Parser {
     level: usize,
     accum: String,
-    list_stack: Vec<Vec<Object>>,
+    list_stack: Vec<Frame>,
     state: State,
-    quoting_state: QuotingState,
+    // source tracking: the file being read, the line the next input
+    // starts on, the position of the current character and the start
+    // of the token being built
+    file: Option<String>,
+    first_line: usize,
+    pos: Position,
+    token_start: Position,
+    source_map: Option<SourceMap>,
+    // complete top-level forms not yet taken by next_form
+    forms: VecDeque<(Object, SourceMap)>,
 }
 
 impl Parser {
@@ -31,143 +67,794 @@ impl Parser {
             accum: String::new(),
             list_stack: Vec::new(),
             state: State::ConsumeWhitespace,
-            quoting_state: QuotingState::None,
+            file: None,
+            first_line: 1,
+            pos: Position::new(1, 1),
+            token_start: Position::new(1, 1),
+            source_map: None,
+            forms: VecDeque::new(),
         }
     }
 
+    /// a parser whose spans and errors name file
+    pub fn for_file(file: &str) -> Parser {
+        let mut parser = Parser::new();
+        parser.file = Some(file.to_string());
+        parser
+    }
+
+    /// the line number the input starts on after the next reset,
+    /// for callers that parse a file a piece at a time
+    pub fn set_line(&mut self, line: usize) {
+        self.first_line = line;
+    }
+
+    /// where the parts of the last successfully parsed object came from
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+
+    /// Parse input as a whole. A single form is returned as itself,
+    /// several forms are returned wrapped in an Object::List;
+    /// use parse_all or reader::Reader to keep forms apart.
     pub fn parse(&mut self, input: &str) -> Result<Object, BelError> {
+        let mut forms = self.parse_all(input)?;
+        let obj = match forms.len() {
+            0 => Object::Symbol("nil".to_string()),
+            1 => forms.remove(0),
+            _ => Object::List(forms),
+        };
+        if let Some(source_map) = self.source_map.as_mut() {
+            if source_map.children.len() == 1 {
+                *source_map = source_map.children.remove(0);
+            }
+        }
+        Ok(obj)
+    }
+
+    /// Parse every top-level form in input, in order. The children of
+    /// the resulting source_map are the maps of the forms.
+    pub fn parse_all(&mut self, input: &str) -> Result<Vec<Object>, BelError> {
+        self.reset();
+        self.feed(input)?;
+        self.finish()?;
+        let (forms, sources) = self.forms.drain(..).unzip();
+        let whole = self.span_from(Position::new(self.first_line, 1));
+        self.source_map = Some(SourceMap::new(whole, sources));
+        Ok(forms)
+    }
+
+    /// forget any partial input and start again at first_line
+    pub fn reset(&mut self) {
         self.level = 0;
         self.accum = String::new();
+        self.pos = Position::new(self.first_line, 1);
+        self.token_start = self.pos;
+        self.source_map = None;
+        self.forms.clear();
         // start with an outer list whether we need it or not
-        self.list_stack = vec![Vec::new()];
+        self.list_stack = vec![Frame::new(None, self.pos)];
         self.state = State::ConsumeWhitespace;
-        self.quoting_state = QuotingState::None;
+    }
 
+    /// Add more input. Forms completed by it can be taken with next_form;
+    /// a partial form is kept until the next feed or finish.
+    pub fn feed(&mut self, input: &str) -> Result<(), BelError> {
+        if self.list_stack.is_empty() {
+            self.reset();
+        }
+        self.read(input).map_err(|err| self.located(self.pos, err))
+    }
+
+    /// The end of the input: complete the token being built and
+    /// report BelError::Incomplete if a form is still open.
+    pub fn finish(&mut self) -> Result<(), BelError> {
+        if self.list_stack.is_empty() {
+            self.reset();
+        }
+        match self.state {
+            State::BuildString(delim) | State::StringEscape(delim) => {
+                let err = BelError::Incomplete(format!("missing closing {}", delim));
+                return Err(self.located(self.token_start, err));
+            }
+            State::BuildChar if self.accum.is_empty() => {
+                let err = BelError::Incomplete("escape without char".to_string());
+                return Err(self.located(self.token_start, err));
+            }
+            _ => {}
+        }
+        if let Err(err) = self.finish_build() {
+            return Err(self.located(self.token_start, err));
+        }
+        if self.level > 0 {
+            let err = BelError::Incomplete(format!("invalid level: {}", self.level));
+            Err(self.located(self.list_stack[self.level].start, err))
+        } else if let Some((prefix, span)) = self.list_stack[0].prefixes.last() {
+            let err = BelError::Incomplete(format!("missing expression after {}", prefix));
+            Err(self.located(span.start, err))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// the next complete top-level form, if any; its source map becomes
+    /// the parser's source_map
+    pub fn next_form(&mut self) -> Option<Object> {
+        let (obj, source) = self.forms.pop_front()?;
+        self.source_map = Some(source);
+        Some(obj)
+    }
+
+    /// true while the input so far ends inside a form, a string or
+    /// before the expression a quote prefix needs
+    pub fn needs_more_input(&self) -> bool {
+        let prefixed = match self.list_stack.first() {
+            Some(frame) => !frame.prefixes.is_empty(),
+            None => false,
+        };
+        match self.state {
+            State::BuildString(_) | State::StringEscape(_) => true,
+            State::BuildChar if self.accum.is_empty() => true,
+            // finishing the token will complete any prefix
+            State::BuildSymbol | State::BuildChar => self.level > 0,
+            _ => self.level > 0 || prefixed,
+        }
+    }
+
+    fn read(&mut self, input: &str) -> Result<(), BelError> {
         for c in input.chars() {
-            match c {
-                '(' => {
-                    self.finish_build();
-                    self.start_level();
+            self.read_char(c)?;
+        }
+        Ok(())
+    }
+
+    // on error the position is left at c
+    fn read_char(&mut self, c: char) -> Result<(), BelError> {
+        // the first character after a backslash is always part of the
+        // char, even if it would otherwise be syntax, e.g. \( or \'
+        match self.state {
+            State::BuildChar if self.accum.is_empty() => {
+                self.accum.push(c);
+                self.advance(c);
+                return Ok(());
+            }
+            // a comment runs from ; to the end of the line
+            State::Comment => {
+                if c == '\n' {
+                    self.state = State::ConsumeWhitespace;
                 }
-                // temporary expedient - just capturing a list
-                // Treat an expression in square brackets, e.g.
-                //  [f _ x]
-                // as an abbreviation for
-                //  (fn (_) (f _ x))
-                '[' => {
-                    self.finish_build();
-                    self.start_level();
+                self.advance(c);
+                return Ok(());
+            }
+            State::BuildString(delim) => {
+                self.advance(c);
+                match c {
+                    _ if c == delim => self.finish_string(delim),
+                    '\\' => self.state = State::StringEscape(delim),
+                    _ => self.accum.push(c),
                 }
-                ')' => {
-                    self.finish_build();
-                    if let QuotingState::List = self.quoting_state {
-                        self.finish_level();
-                        self.quoting_state = QuotingState::None;
-                    };
-                    self.finish_level();
+                return Ok(());
+            }
+            State::StringEscape(delim) => {
+                self.accum.push(c);
+                self.state = State::BuildString(delim);
+                self.advance(c);
+                return Ok(());
+            }
+            _ => {}
+        }
+        match c {
+            '(' | '[' => {
+                self.finish_build()?;
+                self.start_level(c);
+            }
+            ')' | ']' => {
+                self.finish_build()?;
+                self.finish_level(c)?;
+            }
+            '\'' => {
+                self.start_prefix("quote", 1)?;
+            }
+            '`' => {
+                self.start_prefix("bquote", 1)?;
+            }
+            ',' => {
+                self.start_prefix("comma", 1)?;
+            }
+            // ,@ is comma-at
+            '@' if self.follows_comma() => {
+                let frame = &mut self.list_stack[self.level];
+                let (_, span) = frame.prefixes.pop().unwrap();
+                let end = Position::new(span.end.line, span.end.column + 1);
+                let span = Span::new(span.file, span.start, end);
+                frame.prefixes.push(("comma-at", span));
+            }
+            '\\' => {
+                self.finish_build()?;
+                self.start_token(State::BuildChar);
+            }
+            '"' | '¦' => {
+                self.finish_build()?;
+                self.start_token(State::BuildString(c));
+            }
+            ';' => {
+                self.finish_build()?;
+                self.state = State::Comment;
+            }
+            _ => match self.state {
+                State::BuildSymbol if c.is_whitespace() => {
+                    self.finish_build()?;
                 }
-                // temporary expedient - just capturing a list
-                ']' => {
-                    self.finish_build();
-                    if let QuotingState::List = self.quoting_state {
-                        self.finish_level();
-                        self.quoting_state = QuotingState::None;
-                    };
-                    self.finish_level();
+                State::BuildChar if c.is_whitespace() => {
+                    self.finish_build()?;
                 }
-                '`' => {
-                    if let QuotingState::None = self.quoting_state {
-                        self.finish_build();
-                        self.start_level();
-                        self.list_stack[self.level].push(Object::Symbol("quote".to_string()));
-                        self.quoting_state = QuotingState::Starting;
-                    } else {
-                        return Err(BelError::ParseError("Can't handle embedded '".to_string()));
-                    }
+                State::ConsumeWhitespace if c.is_whitespace() => {}
+                State::ConsumeWhitespace => {
+                    self.start_token(State::BuildSymbol);
+                    self.accum.push(c);
                 }
-                '\\' => {
-                    self.finish_build();
-                    self.accum.clear();
-                    self.state = State::BuildChar;
-                    if let QuotingState::Starting = self.quoting_state {
-                        self.quoting_state = QuotingState::Atom;
-                    };
+                _ => {
+                    self.accum.push(c);
                 }
-                _ => match self.state {
-                    State::BuildSymbol if c.is_whitespace() => {
-                        self.finish_build();
-                    }
-                    State::BuildChar if c.is_whitespace() => {
-                        self.finish_build();
-                    }
-                    State::ConsumeWhitespace if c.is_whitespace() => {}
-                    State::ConsumeWhitespace => {
-                        self.accum.clear();
-                        self.accum.push(c);
-                        self.state = State::BuildSymbol;
-                        if let QuotingState::Starting = self.quoting_state {
-                            self.quoting_state = QuotingState::Atom;
-                        };
-                    }
-                    _ => {
-                        self.accum.push(c);
-                    }
-                },
+            },
+        }
+        self.advance(c);
+        Ok(())
+    }
+
+    /// Parse every top-level form in input, carrying on after errors.
+    /// After an error inside a form the rest of that form is skipped up
+    /// to the next ( or [ at the start of a line, which is also taken as
+    /// the start of a new form when the current one was never closed.
+    /// Returns the forms that could be read and every error, located.
+    pub fn parse_recovering(&mut self, input: &str) -> (Vec<Object>, Vec<BelError>) {
+        self.reset();
+        let mut errors = Vec::new();
+        let mut skipping = false;
+        for c in input.chars() {
+            let starts_form = self.pos.column == 1 && (c == '(' || c == '[');
+            if starts_form && skipping {
+                skipping = false;
+            } else if starts_form && self.level > 0 {
+                if let State::ConsumeWhitespace = self.state {
+                    let frame = &self.list_stack[self.level];
+                    let err =
+                        BelError::ParseError(format!("unclosed {}", frame.open.unwrap_or(' ')));
+                    errors.push(self.located(frame.start, err));
+                    self.abandon_form();
+                }
+            }
+            if skipping {
+                self.advance(c);
+                continue;
+            }
+            if let Err(err) = self.read_char(c) {
+                errors.push(self.located(self.pos, err));
+                // a stray ) between forms spoils nothing else
+                skipping = self.level > 0;
+                self.abandon_form();
+                self.advance(c);
             }
         }
+        if !skipping {
+            if let Err(err) = self.finish() {
+                errors.push(err);
+            }
+        }
+        let (forms, sources) = self.forms.drain(..).unzip();
+        let whole = self.span_from(Position::new(self.first_line, 1));
+        self.source_map = Some(SourceMap::new(whole, sources));
+        (forms, errors)
+    }
 
-        self.finish_build();
+    // drop the top-level form being built
+    fn abandon_form(&mut self) {
+        self.list_stack.truncate(1);
+        self.list_stack[0].prefixes.clear();
+        self.level = 0;
+        self.accum.clear();
+        self.state = State::ConsumeWhitespace;
+    }
 
-        if self.level > 0 {
-            Err(BelError::ParseError(format!(
-                "invalid level: {}",
-                self.level
-            )))
+    fn follows_comma(&self) -> bool {
+        if let State::ConsumeWhitespace = self.state {
+            if let Some(("comma", span)) = self.list_stack[self.level].prefixes.last() {
+                return span.end == self.pos;
+            }
+        }
+        false
+    }
+
+    fn advance(&mut self, c: char) {
+        if c == '\n' {
+            self.pos = Position::new(self.pos.line + 1, 1);
         } else {
-            let parse_list = self.list_stack[0].clone();
+            self.pos.column += 1;
+        }
+    }
 
-            // if the parser result is a single Object return that
-            // otherwise return an Object::List of the result
-            let obj = match parse_list.len() {
-                0 => Object::Symbol("nil".to_string()),
-                1 => parse_list[0].clone(),
-                _ => Object::List(parse_list),
-            };
-            Ok(obj)
+    fn start_token(&mut self, state: State) {
+        self.accum.clear();
+        self.token_start = self.pos;
+        self.state = state;
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(self.file.clone(), start, self.pos)
+    }
+
+    fn located(&self, start: Position, err: BelError) -> BelError {
+        if err.location().is_some() {
+            return err;
+        }
+        BelError::Located {
+            location: self.span_from(start),
+            error: Box::new(err),
         }
     }
 
-    fn finish_build(&mut self) {
-        match self.state {
+    // errors here are located at the start of the token
+    fn finish_build(&mut self) -> Result<(), BelError> {
+        self.build_token()
+            .map_err(|err| self.located(self.token_start, err))
+    }
+
+    fn build_token(&mut self) -> Result<(), BelError> {
+        let state = std::mem::replace(&mut self.state, State::ConsumeWhitespace);
+        let span = self.span_from(self.token_start);
+        match state {
+            State::BuildSymbol if self.accum == "." => {
+                self.start_dot()?;
+            }
             State::BuildSymbol => {
-                self.list_stack[self.level].push(Object::Symbol(self.accum.clone()));
+                let obj = parse_word(&self.accum)?;
+                let source = SourceMap::uniform(&obj, &span);
+                self.push_object(obj, source);
             }
             State::BuildChar => {
-                self.list_stack[self.level].push(Object::Char(self.accum.clone()));
+                let obj = parse_char(&self.accum)?;
+                self.push_object(obj, SourceMap::new(span, vec![]));
             }
             _ => {}
         };
-        if let QuotingState::Atom = self.quoting_state {
-            self.finish_level();
-            self.quoting_state = QuotingState::None;
+        Ok(())
+    }
+
+    // a string is a list of chars, e.g. "ab" is (\a \b);
+    // a name between bars is a symbol taken as is, e.g. ¦a b¦ or ¦x.y¦
+    fn finish_string(&mut self, delim: char) {
+        let obj = if delim == '¦' {
+            Object::Symbol(self.accum.clone())
+        } else {
+            Object::List(
+                self.accum
+                    .chars()
+                    .map(|c| Object::Char(c.to_string()))
+                    .collect(),
+            )
         };
+        let source = SourceMap::uniform(&obj, &self.span_from(self.token_start));
+        self.push_object(obj, source);
         self.state = State::ConsumeWhitespace;
     }
 
-    fn start_level(&mut self) {
-        self.list_stack.push(Vec::<Object>::new());
+    // When you see
+    //  'x `x ,x ,@x
+    // treat it as an abbreviation for
+    //  (quote x) (bquote x) (comma x) (comma-at x)
+    // the prefix is held until the next object at this level is complete
+    fn start_prefix(&mut self, name: &'static str, width: usize) -> Result<(), BelError> {
+        self.finish_build()?;
+        let end = Position::new(self.pos.line, self.pos.column + width);
+        let span = Span::new(self.file.clone(), self.pos, end);
+        self.list_stack[self.level].prefixes.push((name, span));
+        Ok(())
+    }
+
+    // a lone . inside a list separates the cdr, as in (a b . c)
+    fn start_dot(&mut self) -> Result<(), BelError> {
+        let frame = &mut self.list_stack[self.level];
+        if frame.open.is_none() {
+            return Err(BelError::ParseError("unexpected dot".to_string()));
+        }
+        if frame.dot.is_some() {
+            return Err(BelError::ParseError("duplicate dot".to_string()));
+        }
+        if frame.list.is_empty() {
+            return Err(BelError::ParseError("missing car before dot".to_string()));
+        }
+        if let Some((prefix, _)) = frame.prefixes.last() {
+            return Err(BelError::ParseError(format!(
+                "missing expression after {}",
+                prefix
+            )));
+        }
+        frame.dot = Some(frame.list.len());
+        Ok(())
+    }
+
+    // add a completed object to the current level, wrapping it in any
+    // pending prefixes, innermost (most recent) first
+    fn push_object(&mut self, obj: Object, source: SourceMap) {
+        let frame = &mut self.list_stack[self.level];
+        let mut obj = obj;
+        let mut source = source;
+        while let Some((name, span)) = frame.prefixes.pop() {
+            obj = Object::List(vec![Object::Symbol(name.to_string()), obj]);
+            let whole = Span::new(span.file.clone(), span.start, source.span.end);
+            source = SourceMap::new(whole, vec![SourceMap::new(span, vec![]), source]);
+        }
+        if self.level == 0 {
+            self.forms.push_back((obj, source));
+        } else {
+            frame.list.push(obj);
+            frame.sources.push(source);
+        }
+    }
+
+    fn start_level(&mut self, c: char) {
+        self.list_stack.push(Frame::new(Some(c), self.pos));
         self.level += 1;
         self.state = State::ConsumeWhitespace;
-        if let QuotingState::Starting = self.quoting_state {
-            self.quoting_state = QuotingState::List;
-        };
     }
 
-    fn finish_level(&mut self) {
-        let list = self.list_stack.pop().unwrap();
+    fn finish_level(&mut self, c: char) -> Result<(), BelError> {
+        if self.level == 0 {
+            return Err(BelError::ParseError(format!("unexpected {}", c)));
+        }
+        if let Some((prefix, _)) = self.list_stack[self.level].prefixes.last() {
+            return Err(BelError::ParseError(format!(
+                "missing expression after {}",
+                prefix
+            )));
+        }
+        let frame = self.list_stack.pop().unwrap();
         self.level -= 1;
-        self.list_stack[self.level].push(Object::List(list));
+        let end = Position::new(self.pos.line, self.pos.column + 1);
+        let span = Span::new(self.file.clone(), frame.start, end);
+        let (list, sources) = match frame.dot {
+            Some(n) => dotted_list(frame.list, frame.sources, n, &span)?,
+            None => (Object::List(frame.list), frame.sources),
+        };
+        let (obj, source) = match (frame.open, c) {
+            (Some('('), ')') => (list, SourceMap::new(span, sources)),
+            // Treat an expression in square brackets, e.g.
+            //  [f _ x]
+            // as an abbreviation for
+            //  (fn (_) (f _ x))
+            (Some('['), ']') => {
+                let obj = Object::List(vec![
+                    Object::Symbol("fn".to_string()),
+                    Object::List(vec![Object::Symbol("_".to_string())]),
+                    list,
+                ]);
+                let open = Span::new(self.file.clone(), frame.start, frame.start);
+                let mut source = SourceMap::uniform(&obj, &open);
+                source.span = span.clone();
+                source.children[2] = SourceMap::new(span, sources);
+                (obj, source)
+            }
+            (open, _) => {
+                return Err(BelError::ParseError(format!(
+                    "mismatched brackets: {} closed by {}",
+                    open.unwrap_or(' '),
+                    c
+                )));
+            }
+        };
+        self.push_object(obj, source);
         self.state = State::ConsumeWhitespace;
+        Ok(())
+    }
+}
+
+/// What a token is, as far as the reader is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Open,
+    Close,
+    // ' ` , and the @ of ,@
+    Quote,
+    Symbol,
+    // a symbol that reads as a number, such as 12 or -1.5
+    Number,
+    Char,
+    String,
+    Comment,
+    // text the reader rejects, such as a stray )
+    Error,
+}
+
+/// A token found by tokenize: its kind and the byte range of its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Split input into the tokens the parser reads it as, for editors and
+/// highlighters. Unlike parse it never fails: input may stop anywhere,
+/// and text the parser rejects is an Error token after which reading
+/// starts again, as in parse_recovering.
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut parser = Parser::new();
+    parser.reset();
+    let mut tokens: Vec<Token> = Vec::new();
+    for (i, c) in input.char_indices() {
+        let before = parser.state;
+        let kind = match parser.read_char(c) {
+            Ok(()) => token_kind(before, parser.state, c),
+            Err(_) => {
+                parser.abandon_form();
+                parser.advance(c);
+                Some(TokenKind::Error)
+            }
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let end = i + c.len_utf8();
+        // a token carries on while the parser is still building it
+        let continues = !matches!(before, State::ConsumeWhitespace)
+            && !matches!(
+                kind,
+                TokenKind::Open | TokenKind::Close | TokenKind::Quote | TokenKind::Error
+            );
+        match tokens.last_mut() {
+            Some(last) if continues && last.end == i && last.kind == kind => last.end = end,
+            _ => tokens.push(Token {
+                kind,
+                start: i,
+                end,
+            }),
+        }
+    }
+    for token in &mut tokens {
+        if token.kind == TokenKind::Symbol && is_number(&input[token.start..token.end]) {
+            token.kind = TokenKind::Number;
+        }
+    }
+    tokens
+}
+
+// the kind of token c is part of, given the parser's state before and
+// after reading it; None for whitespace between tokens
+fn token_kind(before: State, after: State, c: char) -> Option<TokenKind> {
+    match before {
+        State::Comment => return Some(TokenKind::Comment),
+        State::BuildString('"') | State::StringEscape('"') => return Some(TokenKind::String),
+        State::BuildString(_) | State::StringEscape(_) => return Some(TokenKind::Symbol),
+        State::BuildChar if !matches!(after, State::BuildChar) || c == '\\' => {}
+        State::BuildChar => return Some(TokenKind::Char),
+        _ => {}
+    }
+    let kind = match c {
+        '(' | '[' => TokenKind::Open,
+        ')' | ']' => TokenKind::Close,
+        '\'' | '`' | ',' => TokenKind::Quote,
+        '\\' => TokenKind::Char,
+        '"' => TokenKind::String,
+        '¦' => TokenKind::Symbol,
+        ';' => TokenKind::Comment,
+        _ if c.is_whitespace() => return None,
+        _ => match (before, after) {
+            // the @ of ,@
+            (State::ConsumeWhitespace, State::ConsumeWhitespace) => TokenKind::Quote,
+            _ => TokenKind::Symbol,
+        },
+    };
+    Some(kind)
+}
+
+// whether word is a number, as parsenum in bel.bel decides: a real
+// such as 1.5, -1/2 or .5, a real and an imaginary part such as 1+2i,
+// or an imaginary part alone such as -2.5i or +i
+fn is_number(word: &str) -> bool {
+    if is_imaginary(word) {
+        return true;
+    }
+    let unsigned = word.strip_prefix(['+', '-']).unwrap_or(word);
+    match unsigned.find(['+', '-']) {
+        Some(i) => is_real(&unsigned[..i]) && is_imaginary(&unsigned[i..]),
+        None => is_real(unsigned),
+    }
+}
+
+// a sign, then a real or nothing, then i; see validi
+fn is_imaginary(word: &str) -> bool {
+    match word
+        .strip_prefix(['+', '-'])
+        .and_then(|w| w.strip_suffix('i'))
+    {
+        Some(digits) => digits.is_empty() || is_real(digits),
+        None => false,
+    }
+}
+
+// a decimal, or a ratio of two; see validr
+fn is_real(word: &str) -> bool {
+    match word.split_once('/') {
+        Some((n, d)) => is_decimal(n) && is_decimal(d),
+        None => is_decimal(word),
+    }
+}
+
+// digits with at most one dot among them; see validd
+fn is_decimal(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && word.contains(|c: char| c.is_ascii_digit())
+        && word.matches('.').count() <= 1
+}
+
+// chars are stored as the character itself, so \sp and the space in
+// "a b" are the same object; see namecs in bel.bel
+fn parse_char(name: &str) -> Result<Object, BelError> {
+    if name.is_empty() {
+        return Err(BelError::ParseError("escape without char".to_string()));
+    }
+    if name.chars().count() == 1 {
+        return Ok(Object::Char(name.to_string()));
+    }
+    let c = match name {
+        "bel" => '\u{7}',
+        "tab" => '\t',
+        "lf" => '\n',
+        "cr" => '\r',
+        "sp" => ' ',
+        _ => {
+            return Err(BelError::ParseError(format!(
+                "unknown named char: \\{}",
+                name
+            )))
+        }
+    };
+    Ok(Object::Char(c.to_string()))
+}
+
+// build (a b . c) from the n objects before the dot and the one after it;
+// a proper list after the dot, as in (a . (b c)), is the same as (a b c)
+fn dotted_list(
+    list: Vec<Object>,
+    sources: Vec<SourceMap>,
+    n: usize,
+    span: &Span,
+) -> Result<(Object, Vec<SourceMap>), BelError> {
+    if list.len() == n {
+        return Err(BelError::ParseError("missing cdr after dot".to_string()));
+    }
+    if list.len() > n + 1 {
+        return Err(BelError::ParseError("duplicate cdr after dot".to_string()));
+    }
+    let mut list = list;
+    let mut sources = sources;
+    let cdr = list.pop().unwrap();
+    let cdr_source = sources.pop().unwrap();
+    match cdr {
+        Object::List(tail) => {
+            list.extend(tail);
+            sources.extend(cdr_source.children);
+            Ok((Object::List(list), sources))
+        }
+        _ if cdr.is_nil() => Ok((Object::List(list), sources)),
+        _ => {
+            // the pair's own map is the one for the enclosing brackets,
+            // so only its car and cdr are returned here
+            let (obj, source) = list.into_iter().zip(sources).rev().fold(
+                (cdr, cdr_source),
+                |(acc, acc_source), (car, car_source)| {
+                    let pair_span = Span::new(span.file.clone(), car_source.span.start, span.end);
+                    (
+                        Object::Pair(Box::new((car, acc))),
+                        SourceMap::new(pair_span, vec![car_source, acc_source]),
+                    )
+                },
+            );
+            Ok((obj, source.children))
+        }
+    }
+}
+
+// Bel expands characters within a symbol name (see parseword in bel.bel):
+//  a.b  -> (a b)
+//  a!b  -> (a 'b)
+//  x|t  -> (t x t)
+//  f:g  -> (compose f g)
+//  ~f   -> (compose no f)
+// | binds loosest, then . and !, then :, then ~
+// A number such as 1.5 is read first, as it is by parsenum, and is
+// left whole
+pub(crate) fn parse_word(word: &str) -> Result<Object, BelError> {
+    if is_number(word) {
+        Ok(Object::Symbol(word.to_string()))
+    } else if word.contains('|') {
+        parse_typecheck(word)
+    } else if word.contains(is_intrasymbol) {
+        parse_symbol_list(word)
+    } else {
+        parse_compose(word)
+    }
+}
+
+fn is_intrasymbol(c: char) -> bool {
+    c == '.' || c == '!'
+}
+
+fn parse_typecheck(word: &str) -> Result<Object, BelError> {
+    if word.matches('|').count() > 1 {
+        return Err(BelError::ParseError(format!("multiple bars: {}", word)));
+    }
+    let parts: Vec<&str> = word.split('|').filter(|p| !p.is_empty()).collect();
+    if parts.len() != 2 {
+        return Err(BelError::ParseError(format!("bad type spec: {}", word)));
+    }
+    let mut list = vec![Object::Symbol("t".to_string())];
+    for part in parts {
+        list.push(parse_word(part)?);
+    }
+    Ok(Object::List(list))
+}
+
+fn parse_symbol_list(word: &str) -> Result<Object, BelError> {
+    // split the word into runs of intrasymbol and other characters,
+    // e.g. a.b!c -> a . b ! c
+    let mut runs: Vec<String> = Vec::new();
+    for c in word.chars() {
+        match runs.last_mut() {
+            Some(run) if run.ends_with(is_intrasymbol) == is_intrasymbol(c) => run.push(c),
+            _ => runs.push(c.to_string()),
+        }
+    }
+    if runs.last().unwrap().ends_with(is_intrasymbol) {
+        return Err(BelError::ParseError(format!("final intrasymbol: {}", word)));
+    }
+    // a leading . or ! applies to upon, e.g. .a -> (upon a)
+    if runs[0].starts_with(is_intrasymbol) {
+        runs.insert(0, "upon".to_string());
+    }
+    runs.insert(0, ".".to_string());
+
+    let mut list = Vec::new();
+    for pair in runs.chunks(2) {
+        let (op, name) = (&pair[0], &pair[1]);
+        if op.len() > 1 {
+            return Err(BelError::ParseError(format!(
+                "double intrasymbol: {}",
+                word
+            )));
+        }
+        let obj = parse_compose(name)?;
+        if op == "!" {
+            list.push(Object::List(vec![Object::Symbol("quote".to_string()), obj]));
+        } else {
+            list.push(obj);
+        }
+    }
+    Ok(Object::List(list))
+}
+
+fn parse_compose(word: &str) -> Result<Object, BelError> {
+    if word.contains(':') {
+        let mut list = vec![Object::Symbol("compose".to_string())];
+        for part in word.split(':').filter(|p| !p.is_empty()) {
+            list.push(parse_no(part));
+        }
+        Ok(Object::List(list))
+    } else {
+        Ok(parse_no(word))
+    }
+}
+
+fn parse_no(word: &str) -> Object {
+    match word.strip_prefix('~') {
+        Some("") => Object::Symbol("no".to_string()),
+        Some(rest) => Object::List(vec![
+            Object::Symbol("compose".to_string()),
+            Object::Symbol("no".to_string()),
+            parse_no(rest),
+        ]),
+        None => Object::Symbol(word.to_string()),
     }
 }
 
@@ -265,7 +952,7 @@ mod tests {
     #[test]
     fn can_parse_quoted_symbol() -> Result<(), BelError> {
         let mut parser = Parser::new();
-        let parse_obj = parser.parse("`a")?;
+        let parse_obj = parser.parse("'a")?;
         if let Object::List(l) = parse_obj {
             assert_eq!(
                 l,
@@ -283,7 +970,7 @@ mod tests {
     #[test]
     fn can_parse_quoted_list() -> Result<(), BelError> {
         let mut parser = Parser::new();
-        let parse_obj = parser.parse("`(a)")?;
+        let parse_obj = parser.parse("'(a)")?;
         if let Object::List(l) = parse_obj {
             assert_eq!(
                 l,
@@ -298,4 +985,476 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn can_parse_backquote_with_commas() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let parse_obj = parser.parse("`(a ,b ,@c)")?;
+        assert_eq!(
+            parse_obj,
+            Object::List(vec![
+                Object::Symbol("bquote".to_string()),
+                Object::List(vec![
+                    Object::Symbol("a".to_string()),
+                    Object::List(vec![
+                        Object::Symbol("comma".to_string()),
+                        Object::Symbol("b".to_string()),
+                    ]),
+                    Object::List(vec![
+                        Object::Symbol("comma-at".to_string()),
+                        Object::Symbol("c".to_string()),
+                    ]),
+                ]),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_nested_quotes() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("''a")?, parser.parse("(quote (quote a))")?);
+        assert_eq!(parser.parse("',a")?, parser.parse("(quote (comma a))")?);
+        assert_eq!(
+            parser.parse("`(a `(b ,(c ,d)))")?,
+            parser.parse("(bquote (a (bquote (b (comma (c (comma d)))))))")?
+        );
+        assert_eq!(
+            parser.parse("('a '(b) 'c)")?,
+            parser.parse("((quote a) (quote (b)) (quote c))")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_quoted_chars() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("'\\'")?, parser.parse("(quote \\')")?);
+        assert_eq!(
+            parser.parse("(\\( \\))")?,
+            Object::List(vec![
+                Object::Char("(".to_string()),
+                Object::Char(")".to_string())
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn quote_without_expression_is_an_error() {
+        let mut parser = Parser::new();
+        assert!(parser.parse("'").is_err());
+        assert!(parser.parse("(a ,)").is_err());
+        assert!(parser.parse("a)").is_err());
+    }
+
+    #[test]
+    fn can_parse_square_brackets_as_fn() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("[f _ x]")?, parser.parse("(fn (_) (f _ x))")?);
+        assert_eq!(parser.parse("[]")?, parser.parse("(fn (_) ())")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_nested_square_brackets() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse("(map [cons _ (car [f _])] xs)")?,
+            parser.parse("(map (fn (_) (cons _ (car (fn (_) (f _))))) xs)")?
+        );
+        assert_eq!(
+            parser.parse("[a [b _]]")?,
+            parser.parse("(fn (_) (a (fn (_) (b _))))")?
+        );
+        assert_eq!(
+            parser.parse("'[f _]")?,
+            parser.parse("(quote (fn (_) (f _)))")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_brackets_are_an_error() {
+        let mut parser = Parser::new();
+        assert!(parser.parse("[f _)").is_err());
+        assert!(parser.parse("(f _]").is_err());
+        assert!(parser.parse("]").is_err());
+    }
+
+    #[test]
+    fn can_parse_intrasymbol_syntax() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("a.b")?, parser.parse("(a b)")?);
+        assert_eq!(parser.parse("a!b")?, parser.parse("(a 'b)")?);
+        assert_eq!(parser.parse("a.b!c")?, parser.parse("(a b 'c)")?);
+        assert_eq!(parser.parse(".a")?, parser.parse("(upon a)")?);
+        assert_eq!(parser.parse("!a")?, parser.parse("(upon 'a)")?);
+        assert_eq!(parser.parse("x|int")?, parser.parse("(t x int)")?);
+        assert_eq!(parser.parse("f:g")?, parser.parse("(compose f g)")?);
+        assert_eq!(parser.parse("~f")?, parser.parse("(compose no f)")?);
+        assert_eq!(parser.parse("~")?, parser.parse("no")?);
+        // numbers are not split
+        assert_eq!(parser.parse("1.5")?, Object::Symbol("1.5".to_string()));
+        assert_eq!(parser.parse("-1/2")?, Object::Symbol("-1/2".to_string()));
+        for number in [".5", "5.", "1.5/2", "1.5+2.5i", "-i", "+1/2i"] {
+            assert_eq!(parser.parse(number)?, Object::Symbol(number.to_string()));
+        }
+        assert_eq!(parser.parse("1.5.2")?, parser.parse("(1 5 2)")?);
+        assert_eq!(parser.parse("a.1.5")?, parser.parse("(a 1 5)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn intrasymbol_syntax_follows_precedence() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        // | binds loosest
+        assert_eq!(
+            parser.parse("n|isa!tab")?,
+            parser.parse("(t n (isa 'tab))")?
+        );
+        // . and ! bind looser than :
+        assert_eq!(parser.parse("f:g.x")?, parser.parse("((compose f g) x)")?);
+        // : binds looser than ~
+        assert_eq!(
+            parser.parse("~f:g")?,
+            parser.parse("(compose (compose no f) g)")?
+        );
+        assert_eq!(
+            parser.parse("(def nth (n|pint xs|pair))")?,
+            parser.parse("(def nth ((t n pint) (t xs pair)))")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bad_intrasymbol_syntax_is_an_error() {
+        let mut parser = Parser::new();
+        assert!(parser.parse("a.").is_err());
+        assert!(parser.parse("a..b").is_err());
+        assert!(parser.parse("a|b|c").is_err());
+        assert!(parser.parse("a|").is_err());
+    }
+
+    #[test]
+    fn can_parse_dotted_lists() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse("(a . b)")?,
+            Object::Pair(Box::new((
+                Object::Symbol("a".to_string()),
+                Object::Symbol("b".to_string())
+            )))
+        );
+        assert_eq!(
+            parser.parse("(a b . c)")?,
+            Object::Pair(Box::new((
+                Object::Symbol("a".to_string()),
+                Object::Pair(Box::new((
+                    Object::Symbol("b".to_string()),
+                    Object::Symbol("c".to_string())
+                )))
+            )))
+        );
+        assert_eq!(parser.parse("(a . (b c))")?, parser.parse("(a b c)")?);
+        assert_eq!(parser.parse("(a . nil)")?, parser.parse("(a)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_dots_are_an_error() {
+        let mut parser = Parser::new();
+        assert!(parser.parse(".").is_err());
+        assert!(parser.parse("(. a)").is_err());
+        assert!(parser.parse("(a .)").is_err());
+        assert!(parser.parse("(a . b c)").is_err());
+        assert!(parser.parse("(a . . b)").is_err());
+    }
+
+    #[test]
+    fn comments_run_to_end_of_line() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert!(parser.parse("; just a comment")?.is_nil());
+        assert_eq!(parser.parse("a ; note")?, parser.parse("a")?);
+        assert_eq!(
+            parser.parse("(def no (x) ; the negation\n  (id x nil))")?,
+            parser.parse("(def no (x) (id x nil))")?
+        );
+        assert_eq!(parser.parse("(a;note\n b)")?, parser.parse("(a b)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn semicolon_in_strings_and_chars_is_not_a_comment() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse(r"(a \; b)")?,
+            Object::List(vec![
+                Object::Symbol("a".to_string()),
+                Object::Char(";".to_string()),
+                Object::Symbol("b".to_string()),
+            ])
+        );
+        assert_eq!(parser.parse("\"a;b\"")?, parser.parse(r"(\a \; \b)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_strings() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("\"a b\"")?, parser.parse("(\\a \\sp \\b)")?);
+        assert_eq!(
+            parser.parse(r#""say \"hi\"""#)?,
+            parser.parse(r#"(\s \a \y \sp \" \h \i \")"#)?
+        );
+        assert_eq!(parser.parse("\"\"")?, parser.parse("()")?);
+        assert!(parser.parse("\"abc").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_named_chars() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("\\sp")?, Object::Char(" ".to_string()));
+        assert_eq!(parser.parse("\\lf")?, Object::Char("\n".to_string()));
+        assert!(parser.parse("\\nosuchchar").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_spans_of_forms_and_sub_forms() -> Result<(), BelError> {
+        let mut parser = Parser::for_file("test.bel");
+        parser.parse("(def no (x)\n  (id x 'nil))")?;
+        let source_map = parser.source_map().unwrap();
+
+        assert_eq!(source_map.span.start, Position::new(1, 1));
+        assert_eq!(source_map.span.end, Position::new(2, 15));
+        let id = source_map.get(&[3, 0]).unwrap();
+        assert_eq!(id.span.start, Position::new(2, 4));
+        assert_eq!(id.span.end, Position::new(2, 6));
+        let quoted = source_map.get(&[3, 2]).unwrap();
+        assert_eq!(quoted.span.start, Position::new(2, 9));
+        assert_eq!(quoted.span.end, Position::new(2, 13));
+        assert_eq!(quoted.span.to_string(), "test.bel:2:9");
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_line_offsets_spans() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        parser.set_line(10);
+        parser.parse("\n  a")?;
+        let source_map = parser.source_map().unwrap();
+        assert_eq!(source_map.span.start, Position::new(11, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors_report_location() {
+        let mut parser = Parser::for_file("test.bel");
+
+        let err = parser.parse("(a)\n  (b (c)").unwrap_err();
+        assert_eq!(err.location().unwrap().start, Position::new(2, 3));
+        assert_eq!(
+            err.to_string(),
+            "test.bel:2:3: incomplete input: invalid level: 1"
+        );
+
+        let err = parser.parse("(a\n b))").unwrap_err();
+        assert_eq!(err.location().unwrap().start, Position::new(2, 4));
+    }
+
+    #[test]
+    fn parse_all_keeps_forms_apart() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let forms = parser.parse_all("(set a b) (set c d)")?;
+        assert_eq!(
+            forms,
+            vec![parser.parse("(set a b)")?, parser.parse("(set c d)")?]
+        );
+        assert_eq!(parser.parse_all("(a b)")?, vec![parser.parse("(a b)")?]);
+        assert!(parser.parse_all("; nothing")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_feed_input_incrementally() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        parser.reset();
+
+        parser.feed("(a ,")?;
+        assert!(parser.needs_more_input());
+        assert!(parser.next_form().is_none());
+        parser.feed("@b \"c")?;
+        assert!(parser.needs_more_input());
+        parser.feed("\") 'd")?;
+        assert!(!parser.needs_more_input());
+        assert_eq!(parser.next_form(), Some(parser.parse("(a ,@b \"c\")")?));
+
+        // the symbol d may continue in the next input
+        parser.reset();
+        parser.feed("'d")?;
+        assert!(parser.next_form().is_none());
+        parser.finish()?;
+        assert_eq!(parser.next_form(), Some(parser.parse("'d")?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn incomplete_input_is_not_a_syntax_error() {
+        let mut parser = Parser::new();
+        for input in &["(a", "'", "\"abc", "(a (b)", "\\"] {
+            let err = parser.parse(input).unwrap_err();
+            assert!(err.is_incomplete(), "{}: {}", input, err);
+        }
+        for input in &["a)", "(a]", "(. a)", "\\nosuchchar"] {
+            let err = parser.parse(input).unwrap_err();
+            assert!(!err.is_incomplete(), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn recovering_parse_collects_every_error() -> Result<(), BelError> {
+        let mut parser = Parser::for_file("test.bel");
+        let input = "(set a b)\n\
+                     (def f (x)\n  (g x)\n\
+                     (set c d))\n\
+                     )\n\
+                     (set e (f . . g))\n\
+                     (set h \\nosuchchar)\n\
+                     (set i [j _)\n\
+                     (set k l)\n\
+                     (set m";
+        let (forms, errors) = parser.parse_recovering(input);
+
+        assert_eq!(
+            forms,
+            vec![
+                parser.parse("(set a b)")?,
+                parser.parse("(set c d)")?,
+                parser.parse("(set k l)")?,
+            ]
+        );
+        let locations: Vec<String> = errors
+            .iter()
+            .map(|err| err.location().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                "test.bel:2:1",
+                "test.bel:4:10",
+                "test.bel:5:1",
+                "test.bel:6:13",
+                "test.bel:7:8",
+                "test.bel:8:12",
+                "test.bel:10:1",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn recovering_parse_of_good_input_has_no_errors() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let input = "(set a b)\n\n(def f (x)\n  (g x))\n";
+        let (forms, errors) = parser.parse_recovering(input);
+
+        assert!(errors.is_empty());
+        assert_eq!(forms, parser.parse_all(input)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_symbols_between_bars() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("¦a b¦")?, Object::Symbol("a b".to_string()));
+        assert_eq!(parser.parse("¦x.y¦")?, Object::Symbol("x.y".to_string()));
+        assert_eq!(parser.parse(r"¦a\¦b¦")?, Object::Symbol("a¦b".to_string()));
+        assert!(parser.parse("¦abc").unwrap_err().is_incomplete());
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_starts_a_symbol() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse("#foo")?, Object::Symbol("#foo".to_string()));
+        assert_eq!(parser.parse("(a#b #1)")?, parser.parse("(¦a#b¦ ¦#1¦)")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_finds_what_the_reader_reads() {
+        let input = "(def f (x) ; note\n  `(\\sp ,@x \"a b\" #x (12 -3) ¦a b¦))";
+        let tokens: Vec<(TokenKind, &str)> = tokenize(input)
+            .into_iter()
+            .map(|token| (token.kind, &input[token.start..token.end]))
+            .collect();
+        use TokenKind::*;
+        assert_eq!(
+            tokens,
+            [
+                (Open, "("),
+                (Symbol, "def"),
+                (Symbol, "f"),
+                (Open, "("),
+                (Symbol, "x"),
+                (Close, ")"),
+                (Comment, "; note\n"),
+                (Quote, "`"),
+                (Open, "("),
+                (Char, "\\sp"),
+                (Quote, ","),
+                (Quote, "@"),
+                (Symbol, "x"),
+                (String, "\"a b\""),
+                (Symbol, "#x"),
+                (Open, "("),
+                (Number, "12"),
+                (Number, "-3"),
+                (Close, ")"),
+                (Symbol, "¦a b¦"),
+                (Close, ")"),
+                (Close, ")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_marks_errors_and_carries_on() {
+        let kinds: Vec<TokenKind> = tokenize("a) (b \"c")
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::Symbol,
+                TokenKind::Error,
+                TokenKind::Open,
+                TokenKind::Symbol,
+                TokenKind::String
+            ]
+        );
+    }
 }
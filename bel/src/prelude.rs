@@ -0,0 +1,54 @@
+//! The standard library: bel.bel, the definition of Bel in Bel.
+
+use crate::environment::Environment;
+use crate::loader::{self, LoadReport};
+use crate::object::Object;
+
+/// the text of the prelude, compiled into the crate
+pub const SOURCE: &str = include_str!("../data/bel.bel");
+
+// what the interpreter provides itself, which bel.bel defines in terms
+// of things it does not have yet, such as streams for load
+const NATIVE: &[&str] = &["nil", "t", "o", "apply", "load"];
+
+/// Evaluates the prelude in env. Forms this interpreter cannot yet
+/// evaluate are skipped; the report says which they are. The globals
+/// in NATIVE keep the values env gave them.
+pub fn load(env: &mut Environment) -> LoadReport {
+    let native: Vec<(String, Object)> = NATIVE
+        .iter()
+        .filter_map(|name| Some((name.to_string(), env.globals.get(*name)?.clone())))
+        .collect();
+    let report = loader::load_report_str(env, SOURCE, "bel.bel");
+    for (name, value) in native {
+        // so its source is not given as bel.bel either
+        env.unbind(&name);
+        env.globals.insert(name, value);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prelude_binds_the_standard_library() {
+        let env = Environment::with_prelude();
+        for name in ["no", "list", "map", "let", "reduce"] {
+            assert!(env.globals.contains_key(name), "{} is unbound", name);
+        }
+        assert!(env.globals["map"].is_function());
+        assert!(env.globals["let"].is_macro());
+
+        let core = Environment::new();
+        for name in ["nil", "t", "o", "apply", "load"] {
+            assert_eq!(
+                env.globals[name], core.globals[name],
+                "{} was rebound",
+                name
+            );
+            assert_eq!(env.definition_source(name), None);
+        }
+    }
+}
@@ -1,19 +1,21 @@
 use std::collections::HashMap;
 
+use crate::environment::Environment;
 use crate::error::BelError;
 use crate::object::Object;
 
-pub type PrimFunc = fn(&[Object]) -> Result<Object, BelError>;
+pub type PrimFunc = fn(&mut Environment, &[Object]) -> Result<Object, BelError>;
 
 pub fn load_primatives() -> HashMap<String, PrimFunc> {
     let mut prim: HashMap<String, PrimFunc> = HashMap::new();
 
     prim.insert("id".to_string(), id);
+    prim.insert("load".to_string(), load);
 
     prim
 }
 
-fn id(params: &[Object]) -> Result<Object, BelError> {
+fn id(_env: &mut Environment, params: &[Object]) -> Result<Object, BelError> {
     // id is true if
     // * there are two arguments
     // * they are both symbols
@@ -31,3 +33,15 @@ fn id(params: &[Object]) -> Result<Object, BelError> {
 
     Ok(result)
 }
+
+// (load name) evaluates the forms in the file name resolves to;
+// see Environment::resolve
+fn load(env: &mut Environment, params: &[Object]) -> Result<Object, BelError> {
+    match params {
+        [name] => env.load_file(name),
+        _ => Err(BelError::Load(format!(
+            "expected one file name, found {} arguments",
+            params.len()
+        ))),
+    }
+}
@@ -0,0 +1,507 @@
+use crate::object::Object;
+use crate::parser;
+
+/// Prints objects as Bel text, following print, prn and pr in bel.bel.
+/// What print writes, the parser reads back as the same object
+/// (an empty list is written as nil).
+///
+/// Bel's print labels pairs that appear more than once, #1=(a . #1),
+/// (see namedups). An Object owns its parts, so no two parts are ever
+/// the same pair and no object contains itself: printing always ends
+/// and needs no labels. Nor can the parser read labels back into shared
+/// structure, so it reads # as an ordinary symbol character.
+#[derive(Debug, Clone, Default)]
+pub struct Printer {
+    /// write closures as <function (x)> and macros as <macro (x)>
+    /// instead of as the lists they are
+    pub brief_functions: bool,
+    /// write at most this many elements of a list, then ...
+    pub print_length: Option<usize>,
+    /// write lists nested deeper than this as #
+    pub print_level: Option<usize>,
+}
+
+// forms whose first few arguments belong on the first line, with the
+// rest, the body, indented by two, e.g.
+//  (def no (x)
+//    (id x nil))
+const BODY_FORMS: &[(&str, usize)] = &[
+    ("def", 2),
+    ("mac", 2),
+    ("fn", 1),
+    ("let", 2),
+    ("with", 1),
+    ("withs", 1),
+    ("do", 0),
+    ("form", 2),
+    ("syn", 2),
+    ("vir", 2),
+    ("loc", 2),
+];
+
+impl Printer {
+    pub fn new() -> Printer {
+        Printer::default()
+    }
+
+    /// a printer for showing values to people, e.g. in the REPL
+    pub fn brief() -> Printer {
+        Printer {
+            brief_functions: true,
+            ..Printer::default()
+        }
+    }
+
+    /// Readable text for obj; see print in bel.bel.
+    /// Text cut short by print_length or print_level does not read back.
+    pub fn print(&self, obj: &Object) -> String {
+        let mut out = String::new();
+        self.write(&mut out, obj, 0);
+        out
+    }
+
+    /// like print, but a char or string is written as its text;
+    /// see prnice in bel.bel
+    pub fn pr(&self, obj: &Object) -> String {
+        match obj {
+            Object::Char(c) => c.to_string(),
+            _ if is_string(obj) => string_chars(obj).collect(),
+            _ => self.print(obj),
+        }
+    }
+
+    /// Like print, but with lines broken and indented so that, where
+    /// the atoms allow, none is wider than width. Calls line their
+    /// arguments up under the first one and forms with a body, such
+    /// as def and let, indent the body by two, as bel.bel is laid out.
+    pub fn pretty(&self, obj: &Object, width: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, obj, 0, width);
+        out
+    }
+
+    fn write(&self, out: &mut String, obj: &Object, depth: usize) {
+        match obj {
+            Object::Symbol(name) => write_symbol(out, name),
+            Object::Char(c) => write_char(out, c),
+            Object::Stream => out.push_str("<stream>"),
+            Object::List(list) if list.is_empty() => out.push_str("nil"),
+            _ => {
+                if let Some((prefix, arg)) = quote_abbreviation(obj) {
+                    out.push_str(prefix);
+                    self.write(out, arg, depth);
+                } else if is_string(obj) {
+                    write_string(out, obj);
+                } else if self.too_deep(depth) {
+                    out.push('#');
+                } else if self.brief_functions && obj.is_function() {
+                    self.write_brief(out, "function", obj, depth);
+                } else if self.brief_functions && obj.is_macro() {
+                    if let Object::List(list) = obj {
+                        self.write_brief(out, "macro", &list[2], depth);
+                    }
+                } else {
+                    self.write_list(out, obj, depth);
+                }
+            }
+        }
+    }
+
+    fn too_deep(&self, depth: usize) -> bool {
+        match self.print_level {
+            Some(level) => depth >= level,
+            None => false,
+        }
+    }
+
+    // the elements of obj to write, and whether some were left out
+    fn shown<'a>(&self, items: &'a [&'a Object]) -> (&'a [&'a Object], bool) {
+        match self.print_length {
+            Some(length) if items.len() > length => (&items[..length], true),
+            _ => (items, false),
+        }
+    }
+
+    // (lit clo nil p e) is written <function p>
+    fn write_brief(&self, out: &mut String, kind: &str, function: &Object, depth: usize) {
+        out.push('<');
+        out.push_str(kind);
+        if let Object::List(list) = function {
+            out.push(' ');
+            self.write(out, &list[3], depth + 1);
+        }
+        out.push('>');
+    }
+
+    // see prpair and prelts in bel.bel
+    fn write_list(&self, out: &mut String, obj: &Object, depth: usize) {
+        let (items, tail) = elements(obj);
+        let (shown, cut) = self.shown(&items);
+        out.push('(');
+        for (i, item) in shown.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            self.write(out, item, depth + 1);
+        }
+        if cut {
+            out.push_str(" ...");
+        } else if let Some(tail) = tail {
+            out.push_str(" . ");
+            self.write(out, tail, depth + 1);
+        }
+        out.push(')');
+    }
+
+    fn write_pretty(&self, out: &mut String, obj: &Object, depth: usize, width: usize) {
+        let column = current_column(out);
+        let mut flat = String::new();
+        self.write(&mut flat, obj, depth);
+        let fits = column + flat.chars().count() <= width;
+        if fits || !flat.starts_with(['(', '\'', '`', ',']) {
+            out.push_str(&flat);
+            return;
+        }
+        if let Some((prefix, arg)) = quote_abbreviation(obj) {
+            out.push_str(prefix);
+            self.write_pretty(out, arg, depth, width);
+            return;
+        }
+
+        let (items, tail) = elements(obj);
+        let (shown, cut) = self.shown(&items);
+        // how many elements go on the first line, and the column
+        // of the lines that follow
+        let (first_line, indent) = match items[0] {
+            Object::Symbol(name) => match BODY_FORMS.iter().find(|(form, _)| form == name) {
+                Some((_, args)) => (1 + args, column + 2),
+                None => (2, column + 2 + name.chars().count()),
+            },
+            _ => (1, column + 1),
+        };
+        out.push('(');
+        for (i, item) in shown.iter().enumerate() {
+            if i > 0 && i < first_line {
+                out.push(' ');
+            } else if i > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+            }
+            self.write_pretty(out, item, depth + 1, width);
+        }
+        if cut {
+            out.push_str(" ...");
+        } else if let Some(tail) = tail {
+            out.push_str(" . ");
+            self.write_pretty(out, tail, depth + 1, width);
+        }
+        out.push(')');
+    }
+}
+
+/// readable text for obj
+pub fn print(obj: &Object) -> String {
+    Printer::new().print(obj)
+}
+
+/// text for obj as people read it: chars and strings as themselves
+pub fn pr(obj: &Object) -> String {
+    Printer::new().pr(obj)
+}
+
+// (quote x) is written 'x, and likewise ` , and ,@
+fn quote_abbreviation(obj: &Object) -> Option<(&'static str, &Object)> {
+    if let Object::List(list) = obj {
+        if list.len() == 2 {
+            if let Object::Symbol(name) = &list[0] {
+                let prefix = match name.as_str() {
+                    "quote" => "'",
+                    "bquote" => "`",
+                    "comma" => ",",
+                    "comma-at" => ",@",
+                    _ => return None,
+                };
+                // ,@x would read back as comma-at
+                if let Object::Symbol(arg) = &list[1] {
+                    if prefix == "," && arg.starts_with('@') {
+                        return None;
+                    }
+                }
+                return Some((prefix, &list[1]));
+            }
+        }
+    }
+    None
+}
+
+// the elements of a list or chain of pairs, and the final cdr
+// if it is not nil, e.g. a b and c for (a b . c)
+fn elements(obj: &Object) -> (Vec<&Object>, Option<&Object>) {
+    let mut items = Vec::new();
+    let mut rest = obj;
+    loop {
+        match rest {
+            Object::List(list) => {
+                items.extend(list.iter());
+                return (items, None);
+            }
+            Object::Pair(pair) => {
+                items.push(&pair.0);
+                rest = &pair.1;
+            }
+            _ if rest.is_nil() => return (items, None),
+            _ => return (items, Some(rest)),
+        }
+    }
+}
+
+fn current_column(out: &str) -> usize {
+    match out.rfind('\n') {
+        Some(i) => out[i + 1..].chars().count(),
+        None => out.chars().count(),
+    }
+}
+
+// a string is a non-empty list of chars
+pub(crate) fn is_string(obj: &Object) -> bool {
+    match obj {
+        Object::List(list) => !list.is_empty() && list.iter().all(|o| matches!(o, Object::Char(_))),
+        _ => false,
+    }
+}
+
+pub(crate) fn string_chars(obj: &Object) -> impl Iterator<Item = &str> {
+    let list: &[Object] = match obj {
+        Object::List(list) => list,
+        _ => &[],
+    };
+    list.iter().filter_map(|o| match o {
+        Object::Char(c) => Some(c.as_str()),
+        _ => None,
+    })
+}
+
+fn write_string(out: &mut String, obj: &Object) {
+    out.push('"');
+    for c in string_chars(obj) {
+        if c == "\"" || c == "\\" {
+            out.push('\\');
+        }
+        out.push_str(c);
+    }
+    out.push('"');
+}
+
+// the reverse of namecs in bel.bel
+fn write_char(out: &mut String, c: &str) {
+    out.push('\\');
+    let name = match c {
+        "\u{7}" => "bel",
+        "\t" => "tab",
+        "\n" => "lf",
+        "\r" => "cr",
+        " " => "sp",
+        _ => c,
+    };
+    out.push_str(name);
+}
+
+// a symbol whose name would not read back as itself is written
+// between bars, e.g. ¦a b¦; see prsymbol in bel.bel
+fn write_symbol(out: &mut String, name: &str) {
+    if is_plain_symbol(name) {
+        out.push_str(name);
+    } else {
+        out.push('¦');
+        for c in name.chars() {
+            if c == '¦' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('¦');
+    }
+}
+
+fn is_plain_symbol(name: &str) -> bool {
+    if name.is_empty() || name == "." {
+        return false;
+    }
+    if name
+        .chars()
+        .any(|c| c.is_whitespace() || "()[]'`,\"\\;¦".contains(c))
+    {
+        return false;
+    }
+    match parser::parse_word(name) {
+        Ok(Object::Symbol(word)) => word == name,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BelError;
+
+    #[test]
+    fn can_print_atoms() {
+        assert_eq!(print(&Object::Symbol("a".to_string())), "a");
+        assert_eq!(print(&Object::Char("a".to_string())), "\\a");
+        assert_eq!(print(&Object::Char(" ".to_string())), "\\sp");
+        assert_eq!(print(&Object::Stream), "<stream>");
+        assert_eq!(print(&Object::List(vec![])), "nil");
+    }
+
+    #[test]
+    fn can_print_lists() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        for (input, output) in &[
+            ("(a b (c))", "(a b (c))"),
+            ("(a . b)", "(a . b)"),
+            ("(a b . c)", "(a b . c)"),
+            ("(quote a)", "'a"),
+            ("(a (quote (b c)) `(d ,e ,@f))", "(a '(b c) `(d ,e ,@f))"),
+            ("(quote a b)", "(quote a b)"),
+            ("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\""),
+            ("(\\a \\b)", "\"ab\""),
+            ("[f _]", "(fn (_) (f _))"),
+            ("¦a b¦", "¦a b¦"),
+            ("¦a.b¦", "¦a.b¦"),
+            ("a.b", "(a b)"),
+        ] {
+            assert_eq!(print(&parser.parse(input)?), *output, "{}", input);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn display_uses_printer() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse("(a 'b \"c\" \\d)")?;
+        assert_eq!(obj.to_string(), "(a 'b \"c\" \\d)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pr_writes_chars_and_strings_as_text() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        assert_eq!(pr(&parser.parse("\"a b\"")?), "a b");
+        assert_eq!(pr(&parser.parse("\\a")?), "a");
+        assert_eq!(pr(&parser.parse("(\"a\")")?), "(\"a\")");
+
+        Ok(())
+    }
+
+    #[test]
+    fn brief_printer_abbreviates_functions() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let printer = Printer::brief();
+        let function = parser.parse("(lit clo nil (x) (id x nil))")?;
+        assert_eq!(printer.print(&function), "<function (x)>");
+        assert_eq!(print(&function), "(lit clo nil (x) (id x nil))");
+        let macro_obj = parser.parse("(lit mac (lit clo nil (x) (id x nil)))")?;
+        assert_eq!(printer.print(&macro_obj), "<macro (x)>");
+
+        Ok(())
+    }
+
+    #[test]
+    fn printed_objects_read_back() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        for input in &[
+            "a",
+            "(a b (c d) . e)",
+            "(x '(y) `(z ,w ,@v))",
+            "(comma ¦@a¦)",
+            "\"tab\\there; \\\"quoted\\\" \\\\ \"",
+            "(\\a \\sp \\lf \\( \\; \\\\)",
+            "(¦¦ ¦a b¦ ¦x|y¦ ¦~f¦ ¦f:g¦ ¦(¦ ¦\\¦¦)",
+            "(lit clo nil (x) (id x nil))",
+            "(t x int)",
+        ] {
+            let obj = parser.parse(input)?;
+            let printed = print(&obj);
+            assert_eq!(parser.parse(&printed)?, obj, "{} -> {}", input, printed);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn print_length_and_level_cut_output_short() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse("(a (b (c (d))) e f . g)")?;
+
+        let printer = Printer {
+            print_length: Some(2),
+            ..Printer::default()
+        };
+        assert_eq!(printer.print(&obj), "(a (b (c (d))) ...)");
+        let printer = Printer {
+            print_level: Some(2),
+            ..Printer::default()
+        };
+        assert_eq!(printer.print(&obj), "(a (b #) e f . g)");
+        // strings are not cut
+        assert_eq!(printer.print(&parser.parse("((\"abc\"))")?), "((\"abc\"))");
+        let printer = Printer {
+            print_length: Some(2),
+            ..Printer::default()
+        };
+        assert_eq!(printer.print(&parser.parse("\"abc\"")?), "\"abc\"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_keeps_short_forms_on_one_line() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse("(def no (x) (id x nil))")?;
+        assert_eq!(Printer::new().pretty(&obj, 80), "(def no (x) (id x nil))");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_indents_bodies_and_aligns_arguments() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse(
+            "(def reduce (f xs) (if (no (cdr xs)) (car xs) (f (car xs) (reduce f (cdr xs)))))",
+        )?;
+        assert_eq!(
+            Printer::new().pretty(&obj, 40),
+            "(def reduce (f xs)\n\
+             \x20 (if (no (cdr xs))\n\
+             \x20     (car xs)\n\
+             \x20     (f (car xs) (reduce f (cdr xs)))))"
+        );
+
+        let obj = parser.parse("(let x (car ys) (cons x (map [f _ x] ys)))")?;
+        assert_eq!(
+            Printer::new().pretty(&obj, 30),
+            "(let x (car ys)\n\
+             \x20 (cons x\n\
+             \x20       (map (fn (_) (f _ x))\n\
+             \x20            ys)))"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_output_reads_back() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse(
+            "(mac bquote (e) `(let (sub change) ,(bqex e nil) (if change sub (list 'quote e))))",
+        )?;
+        for width in [10, 20, 40, 80] {
+            let printed = Printer::new().pretty(&obj, width);
+            assert_eq!(parser.parse(&printed)?, obj, "{}", printed);
+        }
+
+        Ok(())
+    }
+}
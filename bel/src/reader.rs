@@ -0,0 +1,180 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::error::BelError;
+use crate::object::Object;
+use crate::parser::Parser;
+use crate::source::SourceMap;
+
+/// Reads top-level forms one at a time from a source of Bel text,
+/// a line at a time, so a large file is never held in memory whole.
+///
+/// ```
+/// use bel::reader::Reader;
+///
+/// let forms = Reader::new("(set a b) (set c d)".as_bytes())
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(forms.len(), 2);
+/// ```
+pub struct Reader<R> {
+    input: R,
+    parser: Parser,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Reader<R> {
+        Reader {
+            input,
+            parser: Parser::new(),
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// a reader whose spans and errors name file
+    pub fn for_file(input: R, file: &str) -> Reader<R> {
+        Reader {
+            input,
+            parser: Parser::for_file(file),
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// where the last form returned came from
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.parser.source_map()
+    }
+}
+
+impl<R: Read> Reader<BufReader<R>> {
+    /// a reader for any source, e.g. a File
+    pub fn from_read(input: R) -> Reader<BufReader<R>> {
+        Reader::new(BufReader::new(input))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Object, BelError>;
+
+    // the first error ends the iteration; an input that stops inside a
+    // form ends with an error for which is_incomplete() is true
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(obj) = self.parser.next_form() {
+                return Some(Ok(obj));
+            }
+            if self.finished {
+                return None;
+            }
+            let mut line = String::new();
+            let result = match self.input.read_line(&mut line) {
+                Ok(0) => {
+                    self.finished = true;
+                    self.parser.finish()
+                }
+                Ok(_) => {
+                    // a byte order mark, as on bellanguage.txt, is not text
+                    let text = if self.started {
+                        &line
+                    } else {
+                        line.strip_prefix('\u{feff}').unwrap_or(&line)
+                    };
+                    self.started = true;
+                    self.parser.feed(text)
+                }
+                Err(err) => Err(BelError::Io(err)),
+            };
+            if let Err(err) = result {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_forms_one_at_a_time() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let mut reader = Reader::new("(set a b) (set c d)\n'e".as_bytes());
+
+        assert_eq!(reader.next().unwrap()?, parser.parse("(set a b)")?);
+        assert_eq!(reader.next().unwrap()?, parser.parse("(set c d)")?);
+        assert_eq!(reader.next().unwrap()?, parser.parse("'e")?);
+        assert!(reader.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_forms_across_lines() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let input = "(def no (x)\n  (id x nil))\n\n(no\n\n 'a)";
+        let forms: Vec<Object> = Reader::from_read(input.as_bytes()).collect::<Result<_, _>>()?;
+
+        assert_eq!(
+            forms,
+            vec![
+                parser.parse("(def no (x) (id x nil))")?,
+                parser.parse("(no 'a)")?
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_source_of_each_form() -> Result<(), BelError> {
+        let mut reader = Reader::for_file("a\n  (b\n c)".as_bytes(), "test.bel");
+
+        reader.next().unwrap()?;
+        assert_eq!(
+            reader.source_map().unwrap().span.to_string(),
+            "test.bel:1:1"
+        );
+        reader.next().unwrap()?;
+        assert_eq!(
+            reader.source_map().unwrap().span.to_string(),
+            "test.bel:2:3"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unfinished_input_is_incomplete() {
+        let mut reader = Reader::new("(a) (b".as_bytes());
+
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(err.is_incomplete(), "{}", err);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn syntax_errors_are_not_incomplete() {
+        let mut reader = Reader::new("(a]".as_bytes());
+
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(!err.is_incomplete(), "{}", err);
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark() -> Result<(), BelError> {
+        let mut reader = Reader::new("\u{feff}(a)\n".as_bytes());
+
+        assert_eq!(reader.next().unwrap()?, Parser::new().parse("(a)")?);
+        assert_eq!(
+            reader.source_map().unwrap().span.start,
+            crate::source::Position::new(1, 1)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use crate::error::BelError;
+use crate::object::Object;
+
+/// A position in source text; lines and columns count from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+}
+
+/// The source text covered by a form: from start up to (not including) end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file: Option<String>,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(file: Option<String>, start: Position, end: Position) -> Span {
+        Span { file, start, end }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.start.line, self.start.column),
+            None => write!(f, "{}:{}", self.start.line, self.start.column),
+        }
+    }
+}
+
+/// Objects are plain values, so the parser records where each form came
+/// from in a side table shaped like the form itself:
+/// the children of a List are its elements, the children of a Pair are
+/// its car and cdr.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap {
+    pub span: Span,
+    pub children: Vec<SourceMap>,
+}
+
+impl SourceMap {
+    pub fn new(span: Span, children: Vec<SourceMap>) -> SourceMap {
+        SourceMap { span, children }
+    }
+
+    // a map for obj where every sub-form shares one span, used where
+    // the reader expands text that has no finer structure, e.g. a.b
+    pub(crate) fn uniform(obj: &Object, span: &Span) -> SourceMap {
+        let children = match obj {
+            Object::List(list) => list.iter().map(|o| SourceMap::uniform(o, span)).collect(),
+            Object::Pair(pair) => vec![
+                SourceMap::uniform(&pair.0, span),
+                SourceMap::uniform(&pair.1, span),
+            ],
+            _ => vec![],
+        };
+        SourceMap::new(span.clone(), children)
+    }
+
+    /// the map of the sub-form reached by following path,
+    /// e.g. [2, 0] is the first element of the third element
+    pub fn get(&self, path: &[usize]) -> Option<&SourceMap> {
+        match path.split_first() {
+            None => Some(self),
+            Some((i, rest)) => self.children.get(*i)?.get(rest),
+        }
+    }
+
+    /// the span of the first sub-form of obj (mapped by self) equal to target
+    pub fn find(&self, obj: &Object, target: &Object) -> Option<&Span> {
+        if obj == target {
+            return Some(&self.span);
+        }
+        let subforms: Vec<&Object> = match obj {
+            Object::List(list) => list.iter().collect(),
+            Object::Pair(pair) => vec![&pair.0, &pair.1],
+            _ => vec![],
+        };
+        subforms
+            .into_iter()
+            .zip(self.children.iter())
+            .find_map(|(o, map)| map.find(o, target))
+    }
+
+    /// attach a location to an error from evaluating obj:
+    /// an unbound symbol points at the symbol, anything else at the form
+    pub fn locate(&self, obj: &Object, error: BelError) -> BelError {
+        if error.location().is_some() {
+            return error;
+        }
+        let span = match &error {
+            BelError::UnboundSymbol(name) => self
+                .find(obj, &Object::Symbol(name.to_string()))
+                .unwrap_or(&self.span),
+            _ => &self.span,
+        };
+        BelError::Located {
+            location: span.clone(),
+            error: Box::new(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn can_find_sub_forms() -> Result<(), BelError> {
+        let mut parser = parser::Parser::new();
+        let obj = parser.parse("(a (b c)\n   d)")?;
+        let source_map = parser.source_map().unwrap();
+
+        let span = source_map.find(&obj, &Object::Symbol("c".to_string()));
+        assert_eq!(span.unwrap().start, Position::new(1, 7));
+        let span = source_map.find(&obj, &Object::Symbol("d".to_string()));
+        assert_eq!(span.unwrap().start, Position::new(2, 4));
+        assert!(source_map
+            .find(&obj, &Object::Symbol("e".to_string()))
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn locate_points_unbound_symbols_at_the_symbol() -> Result<(), BelError> {
+        let mut parser = parser::Parser::for_file("test.bel");
+        let obj = parser.parse("(no\n  x)")?;
+        let source_map = parser.source_map().unwrap();
+
+        let err = source_map.locate(&obj, BelError::UnboundSymbol("x".to_string()));
+        assert_eq!(err.to_string(), "test.bel:2:3: unbound symbol: x");
+        let err = source_map.locate(&obj, BelError::InvalidFn("bad".to_string()));
+        assert_eq!(err.to_string(), "test.bel:1:1: invalid fn: bad");
+
+        Ok(())
+    }
+}
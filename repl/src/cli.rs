@@ -0,0 +1,194 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: repl [options] [file.bel ...]
+
+Evaluates the files (- for stdin) and -e expressions in order, then
+stops; with none, reads a program from stdin, or starts the REPL when
+stdin is a terminal.
+
+options:
+  -e, --eval <expr>     evaluate expr and print its value
+  -i, --interactive     start the REPL after the files and expressions
+      --image <file>    start from an image saved with :image
+      --no-prelude      start without the definitions in bel.bel
+      --history <file>  keep the REPL's line history in file
+                        (default: history.txt)
+      --autosave <file> when the REPL exits, :save its definitions to file
+      --init <file>     load file before the first prompt, in place of
+                        $RBEL_INIT or ~/.rbelrc.bel
+      --no-init         load no init file
+  -h, --help            show this message
+
+exit status: 0 on success, 1 if Bel code failed, 2 for bad options";
+
+/// something to evaluate, in the order given
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Script(String),
+    Eval(String),
+    Stdin,
+}
+
+/// what the REPL loads before its first prompt
+#[derive(Debug, PartialEq)]
+pub enum Init {
+    /// $RBEL_INIT, or else ~/.rbelrc.bel if there is one
+    Default,
+    File(PathBuf),
+    None,
+}
+
+impl Init {
+    /// the file to load, if any
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path_in(std::env::var_os("RBEL_INIT"), std::env::var_os("HOME"))
+    }
+
+    // path, given the values of RBEL_INIT and HOME; an empty value is unset
+    fn path_in(&self, rbel_init: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+        match self {
+            Init::Default => match rbel_init.filter(|path| !path.is_empty()) {
+                Some(path) => Some(PathBuf::from(path)),
+                None => home
+                    .filter(|home| !home.is_empty())
+                    .map(|home| PathBuf::from(home).join(".rbelrc.bel"))
+                    .filter(|path| path.exists()),
+            },
+            Init::File(path) => Some(path.clone()),
+            Init::None => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub actions: Vec<Action>,
+    pub interactive: bool,
+    pub image: Option<String>,
+    pub prelude: bool,
+    pub history: PathBuf,
+    pub autosave: Option<PathBuf>,
+    pub init: Init,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            actions: Vec::new(),
+            interactive: false,
+            image: None,
+            prelude: true,
+            history: PathBuf::from("history.txt"),
+            autosave: None,
+            init: Init::Default,
+            help: false,
+        }
+    }
+}
+
+/// the options given by args, the command line without the program name
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value", option))
+        };
+        match arg.as_str() {
+            "-e" | "--eval" => options.actions.push(Action::Eval(value(&arg)?)),
+            "-i" | "--interactive" => options.interactive = true,
+            "--image" => options.image = Some(value(&arg)?),
+            "--no-prelude" => options.prelude = false,
+            "--history" => options.history = PathBuf::from(value(&arg)?),
+            "--autosave" => options.autosave = Some(PathBuf::from(value(&arg)?)),
+            "--init" => options.init = Init::File(PathBuf::from(value(&arg)?)),
+            "--no-init" => options.init = Init::None,
+            "-h" | "--help" => options.help = true,
+            "-" => options.actions.push(Action::Stdin),
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option {}", arg));
+            }
+            _ => options.actions.push(Action::Script(arg)),
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_actions_in_order() -> Result<(), String> {
+        let options = parse(args(
+            "a.bel -e 'x --no-prelude b.bel - --history h -i --autosave s.bel --init r.bel",
+        ))?;
+        assert_eq!(
+            options.actions,
+            [
+                Action::Script("a.bel".to_string()),
+                Action::Eval("'x".to_string()),
+                Action::Script("b.bel".to_string()),
+                Action::Stdin
+            ]
+        );
+        assert!(!options.prelude);
+        assert!(options.interactive);
+        assert_eq!(options.history, PathBuf::from("h"));
+        assert_eq!(options.autosave, Some(PathBuf::from("s.bel")));
+        assert_eq!(options.init, Init::File(PathBuf::from("r.bel")));
+        assert_eq!(parse(args("--init r.bel --no-init"))?.init, Init::None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_init_file() -> Result<(), std::io::Error> {
+        let home = std::env::temp_dir().join(format!("{}-cli-home", std::process::id()));
+        std::fs::create_dir_all(&home)?;
+        let os = |s: &str| Some(OsString::from(s));
+        let rc = home.join(".rbelrc.bel");
+
+        assert_eq!(Init::Default.path_in(None, Some(home.clone().into())), None);
+        std::fs::write(&rc, "")?;
+        assert_eq!(
+            Init::Default.path_in(None, Some(home.clone().into())),
+            Some(rc.clone())
+        );
+        assert_eq!(
+            Init::Default.path_in(os(""), Some(home.clone().into())),
+            Some(rc)
+        );
+        assert_eq!(
+            Init::Default.path_in(os("r.bel"), Some(home.clone().into())),
+            Some(PathBuf::from("r.bel"))
+        );
+        assert_eq!(
+            Init::File(PathBuf::from("f.bel")).path_in(os("r.bel"), None),
+            Some(PathBuf::from("f.bel"))
+        );
+        assert_eq!(
+            Init::None.path_in(os("r.bel"), Some(home.clone().into())),
+            None
+        );
+
+        std::fs::remove_dir_all(&home)
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert_eq!(
+            parse(args("--bogus")),
+            Err("unknown option --bogus".to_string())
+        );
+        assert_eq!(parse(args("a.bel -e")), Err("-e needs a value".to_string()));
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::time::Instant;
+
+use anyhow::{anyhow, Error};
+use bel::parser::Parser;
+use bel::printer::Printer;
+use bel::reader::Reader;
+use bel::{image, loader};
+
+use crate::{printer_for, Session};
+
+/// what the REPL does after a command
+pub enum Next {
+    Read,
+    Quit,
+}
+
+/// A REPL command: what to type, what it takes, what it does for
+/// :help, and the function that does it, given the text after the name
+pub struct Command {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub help: &'static str,
+    run: fn(&mut Session, &str) -> Result<Next, Error>,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: ":help",
+        args: "",
+        help: "list the commands",
+        run: help,
+    },
+    Command {
+        name: ":globals",
+        args: "",
+        help: "show every global in the core namespace",
+        run: globals,
+    },
+    Command {
+        name: ":describe",
+        args: "<symbol>",
+        help: "show the value of a global, what kind it is and where it was defined",
+        run: describe,
+    },
+    Command {
+        name: ":expand",
+        args: "<form>",
+        help: "show a form with its macro calls expanded",
+        run: expand,
+    },
+    Command {
+        name: ":time",
+        args: "<form>",
+        help: "evaluate a form and show how long it took",
+        run: time,
+    },
+    Command {
+        name: ":unbind",
+        args: "<symbol>",
+        help: "remove a global",
+        run: unbind,
+    },
+    Command {
+        name: ":load",
+        args: "<filepath>",
+        help: "evaluate the forms in a file",
+        run: load,
+    },
+    Command {
+        name: ":save",
+        args: "<filepath>",
+        help: "write the definitions made so far to a file :restore can load",
+        run: save,
+    },
+    Command {
+        name: ":restore",
+        args: "<filepath>",
+        help: "evaluate the definitions in a file written by :save",
+        run: restore,
+    },
+    Command {
+        name: ":report",
+        args: "<filepath>",
+        help: "evaluate the forms in a file, carrying on past failures",
+        run: report,
+    },
+    Command {
+        name: ":module",
+        args: "[<name>]",
+        help: "show or change the current module",
+        run: module,
+    },
+    Command {
+        name: ":image",
+        args: "<filepath>",
+        help: "save the environment as an image for repl --image",
+        run: save_image,
+    },
+    Command {
+        name: ":reset",
+        args: "",
+        help: "start again with a new environment and the init file",
+        run: reset,
+    },
+    Command {
+        name: ":quit",
+        args: "",
+        help: "leave the REPL",
+        run: quit,
+    },
+];
+
+/// the names of the commands, for completion
+pub fn names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|command| command.name).collect()
+}
+
+/// Run the command on line, which may be shortened to any prefix that
+/// names only one command, e.g. :q for :quit
+pub fn run(session: &mut Session, line: &str) -> Next {
+    let (name, rest) = match line.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (line.trim(), ""),
+    };
+    let matches: Vec<&Command> = COMMANDS
+        .iter()
+        .filter(|command| command.name == name || command.name.starts_with(name))
+        .collect();
+    let command = match matches.as_slice() {
+        [command] => command,
+        _ => match matches.iter().find(|command| command.name == name) {
+            Some(command) => command,
+            None if matches.is_empty() => {
+                eprintln!("error: unknown command {}; :help lists them", name);
+                return Next::Read;
+            }
+            None => {
+                let names: Vec<&str> = matches.iter().map(|command| command.name).collect();
+                eprintln!("error: {} could be {}", name, names.join(" or "));
+                return Next::Read;
+            }
+        },
+    };
+    match (command.run)(session, rest) {
+        Ok(next) => next,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            Next::Read
+        }
+    }
+}
+
+fn help(_session: &mut Session, _args: &str) -> Result<Next, Error> {
+    for command in COMMANDS {
+        let usage = format!("{} {}", command.name, command.args);
+        println!("{:<22} {}", usage, command.help);
+    }
+    Ok(Next::Read)
+}
+
+fn globals(session: &mut Session, _args: &str) -> Result<Next, Error> {
+    let printer = Printer::brief();
+    let mut names: Vec<&String> = session.env.globals.keys().collect();
+    names.sort();
+    for name in names {
+        println!("({} {})", name, printer.print(&session.env.globals[name]));
+    }
+    Ok(Next::Read)
+}
+
+fn describe(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let name = one_word(args, ":describe <symbol>")?;
+    let value = session.env.lookup(name)?;
+    let kind = if value.is_function() {
+        "function".to_string()
+    } else if value.is_macro() {
+        "macro".to_string()
+    } else {
+        value.t()
+    };
+    println!("{} is a {}", name, kind);
+    let (printer, width) = printer_for(&session.env);
+    let printer = Printer {
+        brief_functions: false,
+        ..printer
+    };
+    println!("{}", printer.pretty(&value, width));
+    if let Some(source) = session.env.definition_source(name) {
+        println!("defined at {}", source);
+    }
+    Ok(Next::Read)
+}
+
+fn expand(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let form = Parser::new().parse(args)?;
+    let expansion = session.env.macroexpand(&form)?;
+    let (printer, width) = printer_for(&session.env);
+    println!("{}", printer.pretty(&expansion, width));
+    Ok(Next::Read)
+}
+
+fn time(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let mut parser = Parser::new();
+    let form = parser.parse(args)?;
+    let start = Instant::now();
+    let result = session.evaluate(&form);
+    let elapsed = start.elapsed();
+    let value = match (result, parser.source_map()) {
+        (Ok(value), _) => value,
+        (Err(err), Some(source_map)) => return Err(source_map.locate(&form, err).into()),
+        (Err(err), None) => return Err(err.into()),
+    };
+    let (printer, width) = printer_for(&session.env);
+    println!("{}", printer.pretty(&value, width));
+    println!("; {:?}", elapsed);
+    Ok(Next::Read)
+}
+
+fn unbind(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let name = one_word(args, ":unbind <symbol>")?;
+    match session.env.unbind(name) {
+        Some(_) => {
+            let module = session.env.current_module().to_string();
+            session.transcript.forget(&module, name);
+            Ok(Next::Read)
+        }
+        None => Err(anyhow!(
+            "{} is not bound in {}",
+            name,
+            session.env.current_module()
+        )),
+    }
+}
+
+fn load(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let path = one_word(args, ":load <filepath>")?;
+    let value = loader::load(&mut session.env, path)?;
+    println!("{}", Printer::brief().print(&value));
+    let module = session.env.current_module().to_string();
+    let path = fs::canonicalize(path)?;
+    session
+        .transcript
+        .record_load(&module, &path.to_string_lossy());
+    Ok(Next::Read)
+}
+
+fn save(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let path = one_word(args, ":save <filepath>")?;
+    fs::write(path, session.transcript.to_string())?;
+    println!("saved {} definitions", session.transcript.len());
+    Ok(Next::Read)
+}
+
+// like :load, but through the session, so that :save keeps what it defines
+fn restore(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let path = one_word(args, ":restore <filepath>")?;
+    let mut reader = Reader::for_file(BufReader::new(File::open(path)?), path);
+    let module = session.env.current_module().to_string();
+    let mut result = Ok(());
+    while let Some(form) = reader.next() {
+        let form = match form {
+            Ok(form) => form,
+            Err(err) => {
+                result = Err(err);
+                break;
+            }
+        };
+        if let Err(err) = session.evaluate(&form) {
+            result = Err(match reader.source_map() {
+                Some(source_map) => source_map.locate(&form, err),
+                None => err,
+            });
+            break;
+        }
+    }
+    session.env.set_current_module(&module);
+    result?;
+    Ok(Next::Read)
+}
+
+fn report(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let path = one_word(args, ":report <filepath>")?;
+    println!("{}", loader::load_report(&mut session.env, path)?);
+    Ok(Next::Read)
+}
+
+fn module(session: &mut Session, args: &str) -> Result<Next, Error> {
+    match args {
+        "" => println!("{}", session.env.current_module()),
+        _ => session
+            .env
+            .set_current_module(one_word(args, ":module [<name>]")?),
+    }
+    Ok(Next::Read)
+}
+
+fn save_image(session: &mut Session, args: &str) -> Result<Next, Error> {
+    let path = one_word(args, ":image <filepath>")?;
+    image::save_file(&session.env, path)?;
+    Ok(Next::Read)
+}
+
+fn reset(session: &mut Session, _args: &str) -> Result<Next, Error> {
+    session.env = session.new_environment()?;
+    session.transcript.clear();
+    session.load_init();
+    Ok(Next::Read)
+}
+
+fn quit(_session: &mut Session, _args: &str) -> Result<Next, Error> {
+    Ok(Next::Quit)
+}
+
+// the argument of a command that takes exactly one
+fn one_word<'a>(args: &'a str, usage: &str) -> Result<&'a str, Error> {
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [word] => Ok(word),
+        _ => Err(anyhow!("usage: {}", usage)),
+    }
+}
@@ -0,0 +1,365 @@
+use std::borrow::Cow::{self, Borrowed, Owned};
+
+use bel::parser::{tokenize, Parser, Token, TokenKind};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+
+/// Line editing for Bel: a form typed over several lines is read a line
+/// at a time after a .. prompt, but one recalled from the history or
+/// pasted is edited as a whole until it is complete; Tab completes global
+/// names, parameters of the enclosing forms, REPL commands and the
+/// files they take; and the input is coloured as the reader sees it,
+/// with the bracket that matches the one at the cursor picked out.
+#[derive(Default)]
+pub struct BelHelper {
+    // false when input is not typed at a terminal; then lines are
+    // always submitted as they come, so that a form left open at the
+    // end is reported
+    multiline: bool,
+    commands: Vec<&'static str>,
+    // the names bound in the environment, kept up to date by the REPL
+    globals: Vec<String>,
+    filenames: FilenameCompleter,
+}
+
+// commands whose argument is a file
+const FILE_COMMANDS: &[&str] = &[":load", ":report", ":image", ":save", ":restore"];
+
+// characters that end a symbol
+const DELIMITERS: &[char] = &['(', ')', '[', ']', '\'', '`', ',', '"', ';'];
+
+impl BelHelper {
+    pub fn new(multiline: bool, commands: &[&'static str]) -> BelHelper {
+        BelHelper {
+            multiline,
+            commands: commands.to_vec(),
+            ..BelHelper::default()
+        }
+    }
+
+    /// the global names to complete, from Environment::global_names
+    pub fn set_globals(&mut self, globals: Vec<String>) {
+        self.globals = globals;
+    }
+
+    fn complete_command(&self, word: &str) -> Vec<Pair> {
+        self.commands
+            .iter()
+            .filter(|command| command.starts_with(word))
+            .map(|command| candidate(command))
+            .collect()
+    }
+
+    fn complete_symbol(&self, input: &str, word: &str) -> Vec<Pair> {
+        let parameters = parameters_in_scope(input);
+        let mut names: Vec<&String> = parameters
+            .iter()
+            .chain(self.globals.iter())
+            .filter(|name| name.starts_with(word))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names.into_iter().map(|name| candidate(name)).collect()
+    }
+}
+
+fn candidate(name: &str) -> Pair {
+    Pair {
+        display: name.to_string(),
+        replacement: name.to_string(),
+    }
+}
+
+// the shape of half-typed input, built from its tokens
+enum Tree {
+    Atom(String),
+    List(Vec<Tree>),
+}
+
+// The parameters of the forms input ends inside, as in
+//  (def f (x y) (let z (car x) (map [... _ ...] ...
+// where x, y, z and _ are in scope
+fn parameters_in_scope(input: &str) -> Vec<String> {
+    let mut open: Vec<(&str, Vec<Tree>)> = vec![("", vec![])];
+    for token in tokenize(input) {
+        let text = &input[token.start..token.end];
+        match token.kind {
+            TokenKind::Open => open.push((text, vec![])),
+            TokenKind::Close if open.len() > 1 => {
+                if let Some((_, list)) = open.pop() {
+                    open.last_mut().unwrap().1.push(Tree::List(list));
+                }
+            }
+            TokenKind::Symbol | TokenKind::Number => {
+                open.last_mut()
+                    .unwrap()
+                    .1
+                    .push(Tree::Atom(text.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut names = Vec::new();
+    for (bracket, list) in &open[1..] {
+        if *bracket == "[" {
+            names.push("_".to_string());
+            continue;
+        }
+        let parameters = match list.first() {
+            Some(Tree::Atom(head)) if head == "fn" || head == "let" => list.get(1),
+            Some(Tree::Atom(head)) if head == "def" || head == "mac" => list.get(2),
+            _ => None,
+        };
+        if let Some(parameters) = parameters {
+            collect_atoms(parameters, &mut names);
+        }
+    }
+    names
+}
+
+fn collect_atoms(tree: &Tree, names: &mut Vec<String>) {
+    match tree {
+        Tree::Atom(atom) if atom != "." => names.push(atom.to_string()),
+        Tree::Atom(_) => {}
+        // an optional parameter (o y d) names only y; d is its default
+        Tree::List(list) if matches!(list.first(), Some(Tree::Atom(head)) if head == "o") => {
+            if let Some(name) = list.get(1) {
+                collect_atoms(name, names);
+            }
+        }
+        Tree::List(list) => list.iter().for_each(|tree| collect_atoms(tree, names)),
+    }
+}
+
+impl Validator for BelHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        // the editor has no continuation prompt, so a single line is
+        // submitted and the REPL prompts .. for the rest of its form
+        if !self.multiline || !input.contains('\n') || input.starts_with(':') {
+            return Ok(ValidationResult::Valid(None));
+        }
+        // input with a syntax error is submitted, so the error is reported
+        let mut parser = Parser::new();
+        match parser.feed(input) {
+            Ok(()) if parser.needs_more_input() => Ok(ValidationResult::Incomplete),
+            _ => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+impl Completer for BelHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        if line.starts_with(':') {
+            return match before.split_once(char::is_whitespace) {
+                None => Ok((0, self.complete_command(before))),
+                Some((command, _)) if FILE_COMMANDS.contains(&command) => {
+                    self.filenames.complete(line, pos, ctx)
+                }
+                Some(_) => Ok((pos, vec![])),
+            };
+        }
+        let start = before
+            .rfind(|c: char| c.is_whitespace() || DELIMITERS.contains(&c))
+            .map_or(0, |i| i + 1);
+        Ok((start, self.complete_symbol(before, &before[start..])))
+    }
+}
+
+impl Hinter for BelHelper {
+    type Hint = String;
+}
+
+// ANSI colours
+const SPECIAL_FORM: &str = "\x1b[1;34m";
+const QUOTED: &str = "\x1b[36m";
+const STRING: &str = "\x1b[32m";
+const CHAR: &str = "\x1b[33m";
+const NUMBER: &str = "\x1b[35m";
+const COMMENT: &str = "\x1b[90m";
+const ERROR: &str = "\x1b[31m";
+const MATCHING_BRACKET: &str = "\x1b[1;4m";
+const RESET: &str = "\x1b[0m";
+
+// the special forms of the evaluator, and those of bel.bel
+const SPECIAL_FORMS: &[&str] = &[
+    "quote", "lit", "if", "apply", "where", "dyn", "after", "ccc", "thread", "set", "def", "mac",
+    "fn", "let", "do", "module", "export", "import",
+];
+
+impl Highlighter for BelHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        if line.starts_with(':') || line.is_empty() {
+            return Borrowed(line);
+        }
+        let tokens = tokenize(line);
+        let matching = matching_brackets(&tokens, pos);
+        let quoted = quoted_tokens(line, &tokens);
+        let mut out = String::with_capacity(line.len() * 2);
+        let mut written = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            let text = &line[token.start..token.end];
+            let colour = match token.kind {
+                _ if matching.contains(&i) => Some(MATCHING_BRACKET),
+                TokenKind::Comment => Some(COMMENT),
+                TokenKind::Error => Some(ERROR),
+                _ if quoted[i] => Some(QUOTED),
+                TokenKind::Symbol if SPECIAL_FORMS.contains(&text) => Some(SPECIAL_FORM),
+                TokenKind::String => Some(STRING),
+                TokenKind::Char => Some(CHAR),
+                TokenKind::Number => Some(NUMBER),
+                _ => None,
+            };
+            out.push_str(&line[written..token.start]);
+            match colour {
+                Some(colour) => {
+                    out.push_str(colour);
+                    out.push_str(text);
+                    out.push_str(RESET);
+                }
+                None => out.push_str(text),
+            }
+            written = token.end;
+        }
+        out.push_str(&line[written..]);
+        Owned(out)
+    }
+
+    // the brackets to pick out change as the cursor moves
+    fn highlight_char(&self, line: &str, _pos: usize) -> bool {
+        !line.is_empty()
+    }
+}
+
+// the indexes of the bracket at or just before pos and its partner
+fn matching_brackets(tokens: &[Token], pos: usize) -> Vec<usize> {
+    let is_bracket = |token: &Token| matches!(token.kind, TokenKind::Open | TokenKind::Close);
+    let at = tokens
+        .iter()
+        .position(|token| is_bracket(token) && token.start == pos)
+        .or_else(|| {
+            tokens
+                .iter()
+                .position(|token| is_bracket(token) && token.end == pos)
+        });
+    let at = match at {
+        Some(at) => at,
+        None => return vec![],
+    };
+    let mut depth = 0;
+    let partner = if tokens[at].kind == TokenKind::Open {
+        tokens.iter().enumerate().skip(at).find(|(_, token)| {
+            match token.kind {
+                TokenKind::Open => depth += 1,
+                TokenKind::Close => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })
+    } else {
+        tokens
+            .iter()
+            .enumerate()
+            .take(at + 1)
+            .rev()
+            .find(|(_, token)| {
+                match token.kind {
+                    TokenKind::Close => depth += 1,
+                    TokenKind::Open => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+    };
+    match partner {
+        Some((partner, _)) => vec![at, partner],
+        None => vec![],
+    }
+}
+
+// which tokens are data because of a ' before them
+fn quoted_tokens(line: &str, tokens: &[Token]) -> Vec<bool> {
+    let mut quoted = vec![false; tokens.len()];
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].kind != TokenKind::Quote || &line[tokens[i].start..tokens[i].end] != "'" {
+            i += 1;
+            continue;
+        }
+        // the quote, any prefixes after it, then one atom or list
+        let mut depth = 0;
+        for (j, token) in tokens.iter().enumerate().skip(i) {
+            quoted[j] = true;
+            i = j + 1;
+            match token.kind {
+                TokenKind::Quote | TokenKind::Comment => continue,
+                TokenKind::Open => depth += 1,
+                TokenKind::Close => depth -= 1,
+                _ => {}
+            }
+            if depth <= 0 {
+                break;
+            }
+        }
+    }
+    quoted
+}
+
+impl Helper for BelHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_of_enclosing_forms_are_in_scope() {
+        let input = "(def f (x (o y (h w))) (let z (car x) (map [g _ ";
+        assert_eq!(parameters_in_scope(input), ["x", "y", "z", "_"]);
+        assert!(parameters_in_scope("(def f (x) x) (").is_empty());
+        assert_eq!(parameters_in_scope("(fn (a . b) \"(fn (c)\" "), ["a", "b"]);
+    }
+
+    #[test]
+    fn highlights_tokens_and_the_matching_bracket() {
+        let helper = BelHelper::new(true, &[]);
+        let line = "(def f (x) '(a \\b))";
+        assert_eq!(
+            helper.highlight(line, 0),
+            "\x1b[1;4m(\x1b[0m\x1b[1;34mdef\x1b[0m f (x) \x1b[36m'\x1b[0m\x1b[36m(\x1b[0m\
+             \x1b[36ma\x1b[0m \x1b[36m\\b\x1b[0m\x1b[36m)\x1b[0m\x1b[1;4m)\x1b[0m"
+        );
+        assert_eq!(matching_brackets(&tokenize(line), 10), [5, 3]);
+        assert!(matching_brackets(&tokenize("(a"), 0).is_empty());
+    }
+
+    #[test]
+    fn completes_symbols_and_commands() {
+        let mut helper = BelHelper::new(true, &[":load", ":globals"]);
+        helper.set_globals(vec!["map".to_string(), "mac".to_string(), "no".to_string()]);
+
+        let names = |candidates: Vec<Pair>| -> Vec<String> {
+            candidates
+                .into_iter()
+                .map(|pair| pair.replacement)
+                .collect()
+        };
+        assert_eq!(names(helper.complete_symbol("(ma", "ma")), ["mac", "map"]);
+        assert_eq!(
+            names(helper.complete_symbol("(fn (mb) (ma", "m")),
+            ["mac", "map", "mb"]
+        );
+        assert_eq!(names(helper.complete_command(":l")), [":load"]);
+    }
+}
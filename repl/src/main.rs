@@ -1,86 +1,315 @@
+mod cli;
+mod commands;
+mod helper;
+mod transcript;
+
 use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process;
 
 use anyhow::{anyhow, Error};
-use bel::environment::Environment;
-use bel::loader;
-use bel::{environment, object, parser};
+use bel::environment::{Environment, Interrupter};
+use bel::error::BelError;
+use bel::printer::Printer;
+use bel::{image, loader};
+use bel::{module, object, parser};
+use commands::Next;
+use helper::BelHelper;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use transcript::Transcript;
 
-fn main() -> Result<(), Error> {
+fn main() {
     env_logger::init();
 
-    let mut rl = Editor::<()>::new();
-    if rl.load_history("history.txt").is_err() {
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    let code = match run(&options) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            1
+        }
+    };
+    process::exit(code);
+}
+
+// false if Bel code failed
+fn run(options: &cli::Options) -> Result<bool, Error> {
+    let stdin = std::io::stdin();
+    let interactive = options.interactive || (options.actions.is_empty() && stdin.is_terminal());
+    // Ctrl-C while Bel code runs, the prelude loading included, stops
+    // it; at the prompt it abandons the form being typed
+    let interrupter = Interrupter::default();
+    if interactive {
+        let handler = interrupter.clone();
+        ctrlc::set_handler(move || handler.interrupt())?;
+    }
+    let mut session = Session::new(options.image.clone(), options.prelude, interrupter)?;
+    session.autosave = options.autosave.clone();
+    session.init = options.init.path();
+    // before the actions, so that what they define wins
+    if interactive {
+        session.load_init();
+    }
+    for action in &options.actions {
+        let result = match action {
+            cli::Action::Script(path) => loader::load(&mut session.env, path).map(|_| ()),
+            cli::Action::Eval(text) => {
+                loader::load_str(&mut session.env, text, "-e").map(|value| {
+                    let (printer, width) = printer_for(&session.env);
+                    println!("{}", printer.pretty(&value, width));
+                })
+            }
+            cli::Action::Stdin => {
+                loader::load_read(&mut session.env, stdin.lock(), "<stdin>").map(|_| ())
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            return Ok(false);
+        }
+    }
+
+    if interactive {
+        repl(&mut session, &options.history)?;
+    } else if options.actions.is_empty() {
+        if let Err(err) = loader::load_read(&mut session.env, stdin.lock(), "<stdin>") {
+            eprintln!("error: {}", err);
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn repl(session: &mut Session, history: &Path) -> Result<(), Error> {
+    let mut rl = Editor::<BelHelper>::new();
+    let multiline = std::io::stdin().is_terminal();
+    rl.set_helper(Some(BelHelper::new(multiline, &commands::names())));
+    if rl.load_history(history).is_err() {
         println!("No previous history.");
     };
 
-    let mut parser = parser::Parser::new();
-    let mut env = environment::Environment::new();
+    // the session's definitions are saved however the REPL ends
+    let result = read_eval_print(&mut rl, session);
+    if let Some(path) = &session.autosave {
+        fs::write(path, session.transcript.to_string())?;
+    }
+    rl.save_history(history)?;
+    result
+}
 
+fn read_eval_print(rl: &mut Editor<BelHelper>, session: &mut Session) -> Result<(), Error> {
+    let mut parser = parser::Parser::new();
     'repl_loop: loop {
-        let readline = rl.readline(">> ");
-        match readline {
-            Ok(line) => {
-                rl.add_history_entry(line.as_str());
-                if line.starts_with(':') {
-                    process_repl_command(&mut env, &line);
+        parser.reset();
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_globals(session.env.global_names());
+        }
+        // the prompt names the current module, unless it is core
+        let mut prompt = match session.env.current_module() {
+            module::CORE => ">> ".to_string(),
+            name => format!("{}>> ", name),
+        };
+        // read until every form typed is complete, and keep the lines
+        // as one history entry
+        let mut entry = String::new();
+        loop {
+            let line = match rl.readline(&prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    println!("CTRL-C (CTRL-D or :quit to exit)");
                     continue 'repl_loop;
                 }
-                let object = match parser.parse(&line) {
-                    Ok(object) => object,
-                    Err(err) => {
-                        eprintln!("error: {:?}", err);
-                        continue 'repl_loop;
+                Err(ReadlineError::Eof) => {
+                    if let Err(err) = parser.finish() {
+                        eprintln!("error: {}", err);
                     }
-                };
-                let locals: HashMap<String, object::Object> = HashMap::new();
-                match env.evaluate(&locals, &object) {
-                    Ok(evaluated_object) => println!("evaluated: {:?}", evaluated_object),
-                    Err(err) => eprintln!("error: {:?}", err),
-                };
+                    println!("CTRL-D");
+                    break 'repl_loop;
+                }
+                Err(err) => {
+                    return Err(anyhow!("Error from readline: {:?}", err));
+                }
+            };
+            if !entry.is_empty() {
+                entry.push('\n');
             }
-            Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
-                break;
+            entry.push_str(&line);
+            // an interrupt that came while reading is not for what follows
+            session.interrupter.clear();
+            if line.starts_with(':') && !parser.needs_more_input() {
+                rl.add_history_entry(entry.as_str());
+                match commands::run(session, &line) {
+                    Next::Read => continue 'repl_loop,
+                    Next::Quit => break 'repl_loop,
+                }
             }
-            Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
-                break;
+            let fed = parser.feed(&line).and_then(|_| parser.feed("\n"));
+            if fed.is_err() || !parser.needs_more_input() {
+                rl.add_history_entry(entry.as_str());
             }
-            Err(err) => {
-                return Err(anyhow!("Error from readline: {:?}", err));
+            evaluate_forms(session, &mut parser);
+            if let Err(err) = fed {
+                eprintln!("error: {}", err);
+                continue 'repl_loop;
+            }
+            if !parser.needs_more_input() {
+                continue 'repl_loop;
             }
+            prompt = ".. ".to_string();
         }
     }
-    rl.save_history("history.txt").unwrap();
 
     Ok(())
 }
 
-fn process_repl_command(env: &mut Environment, line: &str) {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    match parts[0] {
-        ":global" | ":globals" => {
-            println!("global");
-            for (key, value) in &env.globals {
-                println!("({}, {:?}", key, value);
+/// The state of a REPL session
+pub struct Session {
+    pub env: Environment,
+    // the image the session started from, if any
+    image: Option<String>,
+    prelude: bool,
+    // shared by each environment the session makes
+    interrupter: Interrupter,
+    /// the definitions typed so far, for :save
+    pub transcript: Transcript,
+    /// where to save the transcript when the REPL exits
+    pub autosave: Option<PathBuf>,
+    /// loaded when the REPL starts and after :reset
+    pub init: Option<PathBuf>,
+}
+
+impl Session {
+    fn new(
+        image: Option<String>,
+        prelude: bool,
+        interrupter: Interrupter,
+    ) -> Result<Session, Error> {
+        let mut session = Session {
+            env: Environment::new(),
+            image,
+            prelude,
+            interrupter,
+            transcript: Transcript::default(),
+            autosave: None,
+            init: None,
+        };
+        session.env = session.new_environment()?;
+        Ok(session)
+    }
+
+    /// an environment as the session started with
+    pub fn new_environment(&self) -> Result<Environment, Error> {
+        let mut env = match &self.image {
+            Some(path) => image::restore_file(path).map_err(|err| anyhow!("{}: {}", path, err))?,
+            None => Environment::new(),
+        };
+        // so that Ctrl-C can stop the prelude loading too
+        env.set_interrupter(self.interrupter.clone());
+        if self.image.is_none() && self.prelude && env.load_prelude().interrupted() {
+            return Err(anyhow!("interrupted loading the prelude"));
+        }
+        // (load name) also looks in the directories listed in BEL_PATH
+        if let Some(paths) = std::env::var_os("BEL_PATH") {
+            env.search_path = std::env::split_paths(&paths).collect();
+        }
+        Ok(env)
+    }
+
+    /// load the init file, if there is one; a failure is reported
+    /// but leaves the session to carry on without the rest of it
+    pub fn load_init(&mut self) {
+        if let Some(path) = &self.init {
+            let path = path.to_string_lossy();
+            match loader::load(&mut self.env, &path) {
+                Err(err) if err.location().is_some() => eprintln!("error: {}", err),
+                Err(err) => eprintln!("error: {}: {}", path, err),
+                Ok(_) => {}
             }
         }
-        ":load" => {
-            if parts.len() != 2 {
-                println!("load: <filepah>");
-                return;
+    }
+
+    /// evaluate a top-level form, keeping it for :save if it is a
+    /// definition that succeeds
+    pub fn evaluate(&mut self, form: &object::Object) -> Result<object::Object, BelError> {
+        let module = self.env.current_module().to_string();
+        let value = self.env.evaluate(&HashMap::new(), form)?;
+        match self.loaded_path(form) {
+            Some(path) => self.transcript.record_load(&module, &path),
+            None => self.transcript.record(&module, form),
+        }
+        Ok(value)
+    }
+
+    // the file (load "name") loaded, as an absolute path
+    fn loaded_path(&self, form: &object::Object) -> Option<String> {
+        use object::Object::{Char, List, Symbol};
+        let name = match form {
+            List(list) => match list.as_slice() {
+                [Symbol(op), List(chars)] if op == "load" => chars
+                    .iter()
+                    .map(|c| match c {
+                        Char(c) => Some(c.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Option<String>>()?,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let path = fs::canonicalize(self.env.resolve(&name).ok()?).ok()?;
+        Some(path.to_string_lossy().into_owned())
+    }
+}
+
+// evaluate the forms the parser has read, in order, printing each result;
+// an interrupt skips the rest
+fn evaluate_forms(session: &mut Session, parser: &mut parser::Parser) {
+    while let Some(object) = parser.next_form() {
+        match session.evaluate(&object) {
+            Ok(evaluated_object) => {
+                let (printer, width) = printer_for(&session.env);
+                println!("{}", printer.pretty(&evaluated_object, width))
             }
-            match loader::load(env, parts[1]) {
-                Ok(()) => {}
-                Err(err) => {
-                    println!("error: during :load; {:?}", err);
+            Err(err) => {
+                let interrupted = err.is_interrupted();
+                match parser.source_map() {
+                    Some(source_map) => eprintln!("error: {}", source_map.locate(&object, err)),
+                    None => eprintln!("error: {}", err),
+                }
+                if interrupted {
+                    parser.reset();
+                    return;
                 }
             }
-        }
-        _ => {
-            println!("error: unkbnown REPL command {}", line);
-        }
+        };
     }
 }
+
+// results are pretty-printed; (set print-width 60), (set print-length 10)
+// and (set print-level 3) adjust how
+fn printer_for(env: &Environment) -> (Printer, usize) {
+    let setting = |name: &str| match env.globals.get(name) {
+        Some(object::Object::Symbol(value)) => value.parse::<usize>().ok(),
+        _ => None,
+    };
+    let printer = Printer {
+        print_length: setting("print-length"),
+        print_level: setting("print-level"),
+        ..Printer::brief()
+    };
+    (printer, setting("print-width").unwrap_or(80))
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use bel::module;
+use bel::object::Object;
+use bel::printer::Printer;
+
+// the forms :save keeps; the rest only compute values
+const DEFINITIONS: &[&str] = &["def", "mac", "set", "export", "import", "load"];
+
+/// The definitions made in a REPL session that succeeded, in order,
+/// each with the module it was made in. Displayed, it is Bel text
+/// that makes them again when loaded.
+#[derive(Debug, Default)]
+pub struct Transcript {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    module: String,
+    // the global it binds, for :unbind
+    name: Option<String>,
+    text: String,
+}
+
+impl Transcript {
+    /// keep form, made in module, if it is a definition
+    pub fn record(&mut self, module: &str, form: &Object) {
+        let (op, name) = match form {
+            Object::List(list) => match list.as_slice() {
+                [Object::Symbol(op), Object::Symbol(name), ..] => (op, Some(name)),
+                [Object::Symbol(op), ..] => (op, None),
+                _ => return,
+            },
+            _ => return,
+        };
+        if DEFINITIONS.contains(&op.as_str()) {
+            self.entries.push(Entry {
+                module: module.to_string(),
+                name: name.filter(|_| op != "export" && op != "import").cloned(),
+                text: Printer::new().pretty(form, 80),
+            });
+        }
+    }
+
+    /// keep a :load of the file at path, which should be absolute,
+    /// since the saved file may be somewhere else
+    pub fn record_load(&mut self, module: &str, path: &str) {
+        let path = path.replace('\\', "\\\\").replace('"', "\\\"");
+        self.entries.push(Entry {
+            module: module.to_string(),
+            name: None,
+            text: format!("(load \"{}\")", path),
+        });
+    }
+
+    /// drop the definitions of name in module
+    pub fn forget(&mut self, module: &str, name: &str) {
+        self.entries
+            .retain(|entry| entry.module != module || entry.name.as_deref() != Some(name));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// a (module m) form wherever the module changes
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "; definitions saved from a repl session")?;
+        let mut current = module::CORE;
+        for entry in &self.entries {
+            if entry.module != current {
+                current = &entry.module;
+                writeln!(f, "\n(module {})", current)?;
+            }
+            writeln!(f, "\n{}", entry.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bel::error::BelError;
+    use bel::parser::Parser;
+
+    #[test]
+    fn keeps_definitions_in_their_modules() -> Result<(), BelError> {
+        let mut parser = Parser::new();
+        let mut transcript = Transcript::default();
+        for (module, text) in [
+            ("core", "(set a 'b)"),
+            ("core", "'c"),
+            ("m", "(def f (x) x)"),
+            ("m", "(export f)"),
+            ("core", "(set a 'd)"),
+            ("core", "(set e a)"),
+        ] {
+            transcript.record(module, &parser.parse(text)?);
+        }
+        transcript.record_load("core", "/tmp/a \"b\".bel");
+        transcript.forget("core", "a");
+        assert_eq!(transcript.len(), 4);
+        assert_eq!(
+            transcript.to_string(),
+            "; definitions saved from a repl session\n\n\
+             (module m)\n\n(def f (x) x)\n\n(export f)\n\n\
+             (module core)\n\n(set e a)\n\n(load \"/tmp/a \\\"b\\\".bel\")\n"
+        );
+
+        Ok(())
+    }
+}